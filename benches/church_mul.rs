@@ -0,0 +1,142 @@
+//! Times the multiplication of two Church numerals, and counts the allocations
+//! it makes.
+//!
+//! Run with `cargo bench --bench church_mul`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    BenchmarkId, Criterion, Throughput,
+};
+use ulc::ulc::{
+    church::{church_numeral, decode_church},
+    interpretation::interpret,
+    syntax::{term_builder::*, to_builder, Env, Term},
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Measures the number of allocations made rather than the time taken.
+struct Allocations;
+
+impl Measurement for Allocations {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+
+    fn end(&self, start: usize) -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed) - start
+    }
+
+    fn add(&self, v1: &usize, v2: &usize) -> usize {
+        v1 + v2
+    }
+
+    fn zero(&self) -> usize {
+        0
+    }
+
+    fn to_f64(&self, value: &usize) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &AllocationsFormatter
+    }
+}
+
+struct AllocationsFormatter;
+
+impl ValueFormatter for AllocationsFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        _throughput: &Throughput,
+        _values: &mut [f64],
+    ) -> &'static str {
+        "allocs"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "allocs"
+    }
+}
+
+/// `(def mul = λm λn λf (m (n f)) in (mul <m> <n>))`
+fn mul(m: u64, n: u64) -> Term {
+    def(
+        "mul",
+        lam(
+            "m",
+            lam("n", lam("f", neu("m", vec![neu("n", vec![var("f")])]))),
+        ),
+        neu(
+            "mul",
+            vec![
+                to_builder(&church_numeral(m)),
+                to_builder(&church_numeral(n)),
+            ],
+        ),
+    )
+    .into()
+}
+
+/// Benchmarks interpreting `m * n` and decoding the product, for `m = n`.
+fn bench_church_mul<M: Measurement>(c: &mut Criterion<M>, name: &str) {
+    let mut group = c.benchmark_group(name);
+    for n in [5, 10, 20] {
+        let term = mul(n, n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &term, |b, term| {
+            b.iter(|| {
+                let val = interpret(&Env::default(), black_box(term)).unwrap();
+                assert_eq!(decode_church(&val), Ok(n * n));
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_time(c: &mut Criterion) {
+    bench_church_mul(c, "church_mul");
+}
+
+fn bench_allocations(c: &mut Criterion<Allocations>) {
+    bench_church_mul(c, "church_mul_allocations");
+}
+
+criterion_group!(time, bench_time);
+criterion_group! {
+    name = allocations;
+    config = Criterion::default().with_measurement(Allocations);
+    targets = bench_allocations
+}
+criterion_main!(time, allocations);
@@ -0,0 +1,74 @@
+//! Times the interpretation of Church-numeral arithmetic, read back to the
+//! resulting numeral, and of a long chain of `def`s.
+//!
+//! Run with `cargo bench --bench interpret`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ulc::ulc::{
+    church::church_numeral,
+    interpretation::{interpret, normalize},
+    syntax::{term_builder::*, to_builder, Env, Term, TermBuilder},
+};
+
+/// `(def <name> = <op> in (<name> <m> <n>))`
+fn arithmetic(name: &str, op: TermBuilder, m: u64, n: u64) -> Term {
+    def(
+        name,
+        op,
+        neu(
+            name,
+            vec![
+                to_builder(&church_numeral(m)),
+                to_builder(&church_numeral(n)),
+            ],
+        ),
+    )
+    .into()
+}
+
+/// `(def x0 = λx x in (def x1 = x0 in ... (def x<n> = x<n-1> in x<n>)))`
+fn def_chain(n: usize) -> Term {
+    let body = (1..=n).rev().fold(var(&format!("x{}", n)), |body, i| {
+        def(&format!("x{}", i), var(&format!("x{}", i - 1)), body)
+    });
+    def("x0", lam("x", var("x")), body).into()
+}
+
+fn bench_church(c: &mut Criterion) {
+    // λm λn λf λx (m f (n f x))
+    let add = lams(
+        &["m", "n", "f", "x"],
+        neu("m", vec![var("f"), neu("n", vec![var("f"), var("x")])]),
+    );
+    // λm λn λf (m (n f))
+    let mul = lams(&["m", "n", "f"], neu("m", vec![neu("n", vec![var("f")])]));
+
+    let mut group = c.benchmark_group("church");
+    for n in [5, 10, 20] {
+        let term = arithmetic("add", add.clone(), n, n);
+        group.bench_with_input(BenchmarkId::new("add", n), &term, |b, term| {
+            b.iter(|| normalize(&Env::default(), black_box(term)))
+        });
+        let term = arithmetic("mul", mul.clone(), n, n);
+        group.bench_with_input(BenchmarkId::new("mul", n), &term, |b, term| {
+            b.iter(|| normalize(&Env::default(), black_box(term)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_def_chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("def_chain");
+    for n in [10, 100, 200] {
+        let term = def_chain(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &term, |b, term| {
+            b.iter(|| interpret(&Env::default(), black_box(term)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_church, bench_def_chain);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+//! Times the interpretation of a term that repeats the same computations many
+//! times, with and without memoization.
+//!
+//! Run with `cargo bench --bench memoized`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use ulc::ulc::{
+    church::church_numeral,
+    interpretation::{interpret, interpret_memoized, readback},
+    syntax::{term_builder::*, to_builder, Env, Term, TermBuilder},
+};
+
+/// `(def mul = λm λn λf (m (n f)) in (def id = λx x in
+///  (def first = λx0 ... λx<copies> x0 in
+///   (first (mul 20 20 id id) ... (mul 20 20 id id)))))`
+fn repeated_products(copies: usize) -> Term {
+    let product = || {
+        neu(
+            "mul",
+            vec![
+                to_builder(&church_numeral(20)),
+                to_builder(&church_numeral(20)),
+                var("id"),
+                var("id"),
+            ],
+        )
+    };
+    let names: Vec<String> = (0..copies + 1).map(|i| format!("x{}", i)).collect();
+    def(
+        "mul",
+        lam(
+            "m",
+            lam("n", lam("f", neu("m", vec![neu("n", vec![var("f")])]))),
+        ),
+        def(
+            "id",
+            lam("x", var("x")),
+            def(
+                "first",
+                lams(
+                    &names.iter().map(String::as_str).collect::<Vec<&str>>(),
+                    var("x0"),
+                ),
+                neu(
+                    "first",
+                    (0..copies + 1)
+                        .map(|_| product())
+                        .collect::<Vec<TermBuilder>>(),
+                ),
+            ),
+        ),
+    )
+    .into()
+}
+
+fn bench_memoized(c: &mut Criterion) {
+    let mut group = c.benchmark_group("memoized");
+    // a plain interpretation of 100 copies takes long enough that the default
+    // 100 samples would take a couple of minutes
+    group.sample_size(10);
+    for copies in [1, 10, 100] {
+        let term = repeated_products(copies);
+        for (name, interpret) in [
+            ("plain", interpret as fn(&Env, &Term) -> _),
+            ("memoized", interpret_memoized),
+        ] {
+            let val = interpret(&Env::default(), &term).unwrap();
+            assert_eq!(readback(&val), Ok(lam("x", var("x")).into()));
+            group.bench_with_input(BenchmarkId::new(name, copies), &term, |b, term| {
+                b.iter(|| interpret(&Env::default(), black_box(term)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_memoized);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+//! A line-based REPL that interprets terms in a session environment.
+//!
+//! Each line is either a term, whose value is printed, or a definition `let
+//! <name> = <term>`, which adds the value of the term to the session so that
+//! later lines can refer to it by name.
+
+use std::io::{self, BufRead, Write};
+
+use ulc::ulc::{
+    interpretation::interpret,
+    parser::parse,
+    syntax::{resolve, Env, NameIntro},
+};
+
+#[derive(Default)]
+struct Session {
+    env: Env,
+    /// The labels of the bindings in `env`, most recent first.
+    names: Vec<String>,
+}
+
+impl Session {
+    /// Evaluates a line of input, returning what to print.
+    fn eval_line(&mut self, line: &str) -> Result<String, String> {
+        let (name, input) = match line.trim().strip_prefix("let ") {
+            Some(definition) => {
+                let (name, input) = definition
+                    .split_once('=')
+                    .ok_or("expected `let <name> = <term>`")?;
+                let name = name.trim();
+                if name.is_empty() || !name.chars().all(char::is_alphanumeric) {
+                    return Err(format!("invalid name `{}`", name));
+                }
+                (Some(name), input)
+            }
+            None => (None, line),
+        };
+        let builder = parse(input).map_err(|err| err.to_string())?;
+        let term = resolve(&builder, self.names.clone())?;
+        let val = interpret(&self.env, &term).map_err(|err| err.to_string())?;
+        let output = format!("{}", val);
+        if let Some(name) = name {
+            self.env = self.env.extend(NameIntro::new(name), Box::new(val));
+            self.names.insert(0, name.to_string());
+            return Ok(format!("{} = {}", name, output));
+        }
+        Ok(output)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut session = Session::default();
+    let mut stdout = io::stdout();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match session.eval_line(&line) {
+            Ok(output) => writeln!(stdout, "{}", output)?,
+            Err(err) => writeln!(stdout, "error: {}", err)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Session;
+
+    #[test]
+    fn test_eval_line() {
+        let mut session = Session::default();
+        assert_eq!(
+            session.eval_line("let id = λx x"),
+            Ok("id = λ[]x x#0".to_string())
+        );
+        assert_eq!(
+            session.eval_line("let k = \\x \\y x"),
+            Ok("k = λ[]x λy x#1".to_string())
+        );
+        assert_eq!(session.eval_line("k id k"), Ok("λ[]x x#0".to_string()));
+        assert!(session.eval_line("(id").is_err());
+        assert!(session.eval_line("unbound").is_err());
+        assert!(session.eval_line("let = id").is_err());
+        // the session survives errors
+        assert_eq!(session.eval_line("id k"), Ok("λ[]x λy x#1".to_string()));
+    }
+}
@@ -1,2 +1,134 @@
+pub mod church;
+pub mod combinators;
 pub mod interpretation;
+pub mod lint;
+pub mod machine;
+pub mod parser;
+pub mod program;
+pub mod render;
+pub mod store;
 pub mod syntax;
+
+use interpretation::interpret;
+use parser::parse;
+use syntax::{resolve, Env, Val};
+
+/// Parses `input` (see [`parse`]) and interprets the closed term it denotes,
+/// describing any parse, scope, or interpretation error as a string.
+pub fn eval_str(input: &str) -> Result<Val, String> {
+    let builder = parse(input).map_err(|err| err.to_string())?;
+    let term = resolve(&builder, vec![])?;
+    interpret(&Env::default(), &term).map_err(|err| err.to_string())
+}
+
+/// Builds a [`TermBuilder`] from Rust tokens that mirror the syntax that
+/// [`parse`] reads, except that a lambda is written `|x| body` (or
+/// `|x, y| body`), since `\` isn't a Rust token and `λx` would be read as
+/// a single name:
+///
+/// - `|x, ...| <Term>`
+/// - `def x = <Term> in <Term>`, where a binding that itself contains a `def`
+///   must be parenthesized
+/// - `f <Term> ... <Term>`, where each argument is a name, a parenthesized term,
+///   or, if it is the last one, a lambda or a `def`
+/// - `(<Term>) <Term> ... <Term>`, with arguments as above
+/// - `(<Term>)`
+///
+/// For example, `ulc!(def k = |x, y| x in k (|z| z) k)`.
+///
+/// [`TermBuilder`]: syntax::TermBuilder
+#[macro_export]
+macro_rules! ulc {
+    (@def $x:ident [$($binding:tt)+] in $($body:tt)+) => {
+        $crate::ulc::syntax::term_builder::def(
+            stringify!($x),
+            $crate::ulc!($($binding)+),
+            $crate::ulc!($($body)+),
+        )
+    };
+    (@def $x:ident [$($binding:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::ulc!(@def $x [$($binding)* $next] $($rest)*)
+    };
+    (@args [$($done:expr),*]) => {
+        vec![$($done),*]
+    };
+    (@args [$($done:expr),*] | $($rest:tt)+) => {
+        vec![$($done,)* $crate::ulc!(| $($rest)+)]
+    };
+    (@args [$($done:expr),*] def $($rest:tt)+) => {
+        vec![$($done,)* $crate::ulc!(def $($rest)+)]
+    };
+    (@args [$($done:expr),*] $arg:tt $($rest:tt)*) => {
+        $crate::ulc!(@args [$($done,)* $crate::ulc!($arg)] $($rest)*)
+    };
+    (| $($x:ident),+ | $($body:tt)+) => {
+        $crate::ulc::syntax::term_builder::lams(&[$(stringify!($x)),+], $crate::ulc!($($body)+))
+    };
+    (def $x:ident = $($rest:tt)+) => {
+        $crate::ulc!(@def $x [] $($rest)+)
+    };
+    (($($term:tt)+)) => {
+        $crate::ulc!($($term)+)
+    };
+    (($($head:tt)+) $($arguments:tt)+) => {
+        $crate::ulc::syntax::term_builder::apps(
+            $crate::ulc!($($head)+),
+            $crate::ulc!(@args [] $($arguments)+),
+        )
+    };
+    ($f:ident) => {
+        $crate::ulc::syntax::term_builder::var(stringify!($f))
+    };
+    ($f:ident $($arguments:tt)+) => {
+        $crate::ulc::syntax::term_builder::neu(
+            stringify!($f),
+            $crate::ulc!(@args [] $($arguments)+),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        eval_str,
+        parser::parse,
+        syntax::{Env, NameIntro, NameRef, Term, Val},
+    };
+
+    #[test]
+    fn test_eval_str() {
+        assert_eq!(
+            eval_str("def id = λx x in id λy y"),
+            Ok(Val::lam(
+                Env::default(),
+                NameIntro::new("y"),
+                Term::var(NameRef::new("y", 0))
+            ))
+        );
+        assert!(eval_str("λx (x").unwrap_err().starts_with("expected `)`"));
+        assert!(eval_str("λx y").is_err());
+    }
+
+    #[test]
+    fn test_ulc_macro() {
+        for (term, source) in [
+            (ulc!(|x, y| x), "λx λy x"),
+            (ulc!(def f = |x| x in f), "def f = λx x in f"),
+            (
+                ulc!(def k = |x, y| x in k (|z| z) (k k) k),
+                "def k = λx λy x in k (λz z) (k k) k",
+            ),
+            (ulc!(|f| f f |x| f x), "λf f f λx f x"),
+            (
+                ulc!(|f| f (def g = (def h = f in h) in g) def a = f in a),
+                "λf f (def g = def h = f in h in g) def a = f in a",
+            ),
+            (ulc!(|x| (x)), "λx x"),
+            (ulc!((|x| x) y), "(λx x) y"),
+            (ulc!(|f| (f f) (|x| x) |y| y), "λf (f f) (λx x) λy y"),
+            (ulc!((def i = |x| x in i) i i), "(def i = λx x in i) i i"),
+        ] {
+            assert_eq!(term, parse(source).unwrap());
+        }
+    }
+}
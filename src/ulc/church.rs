@@ -0,0 +1,232 @@
+use super::{
+    interpretation::readback,
+    syntax::{shift, NameIntro, NameRef, Term, Val},
+};
+
+/// `λf λx (f (f ... (f x)))`, where `f` is applied `n` times.
+pub fn church_numeral(n: u64) -> Term {
+    Term::lam(
+        NameIntro::new("f"),
+        Term::lam(
+            NameIntro::new("x"),
+            (0..n).fold(Term::var(NameRef::new("x", 0)), |body, _| {
+                Term::neu(NameRef::new("f", 1), vec![body])
+            }),
+        ),
+    )
+}
+
+/// Recovers `n` from a value that reads back to the `n`th Church numeral,
+/// whatever the labels of its binders are.
+pub fn decode_church(val: &Val) -> Result<u64, String> {
+    let term = readback(val).map_err(|err| err.to_string())?;
+    let Term::Lam { body, .. } = &term else {
+        return Err(format!(
+            "expected a Church numeral `λf λx ...` but found {}",
+            term
+        ));
+    };
+    let Term::Lam { body, .. } = body.as_ref() else {
+        return Err(format!(
+            "expected a Church numeral `λf λx ...` but found {}",
+            term
+        ));
+    };
+    let mut n = 0;
+    let mut body = body.as_ref();
+    loop {
+        match body {
+            Term::Var(name) if name.index() == 0 => return Ok(n),
+            Term::Neu {
+                applicant,
+                arguments,
+            } if applicant.index() == 1 && arguments.len() == 1 => {
+                n += 1;
+                body = &arguments[0]
+            }
+            _ => {
+                return Err(format!(
+                "expected `f#1` applied to one argument or `x#0` in a Church numeral but found {}",
+                body
+            ))
+            }
+        }
+    }
+}
+
+/// `λt λf t` if `b` is true, and `λt λf f` otherwise.
+pub fn church_bool(b: bool) -> Term {
+    let index = if b { 1 } else { 0 };
+    let label = if b { "t" } else { "f" };
+    Term::lam(
+        NameIntro::new("t"),
+        Term::lam(NameIntro::new("f"), Term::var(NameRef::new(label, index))),
+    )
+}
+
+/// `λb λt λf (b t f)`, which chooses `t` if `b` is the Church boolean true
+/// and `f` if it is false.
+pub fn church_if() -> Term {
+    Term::lams_term(
+        vec![
+            NameIntro::new("b"),
+            NameIntro::new("t"),
+            NameIntro::new("f"),
+        ],
+        Term::neu(
+            NameRef::new("b", 2),
+            vec![
+                Term::var(NameRef::new("t", 1)),
+                Term::var(NameRef::new("f", 0)),
+            ],
+        ),
+    )
+}
+
+/// Recovers `b` from a value that reads back to the Church boolean `b`, i.e.
+/// that chooses the first of two fresh variables it is applied to if `b` is
+/// true and the second if it is false.
+pub fn decode_church_bool(val: &Val) -> Result<bool, String> {
+    let term = readback(val).map_err(|err| err.to_string())?;
+    if let Term::Lam { body, .. } = &term {
+        if let Term::Lam { body, .. } = body.as_ref() {
+            if let Term::Var(name) = body.as_ref() {
+                return Ok(name.index() == 1);
+            }
+        }
+    }
+    Err(format!(
+        "expected a Church boolean `λt λf t` or `λt λf f` but found {}",
+        term
+    ))
+}
+
+/// `λs (s a b)`, which passes `a` and `b` to a selector `s`.
+pub fn church_pair(a: Term, b: Term) -> Term {
+    Term::lam(
+        NameIntro::new("s"),
+        Term::neu(NameRef::new("s", 0), vec![shift(&a, 0, 1), shift(&b, 0, 1)]),
+    )
+}
+
+/// `λp (p λa λb a)`, which projects the first component of a Church pair.
+pub fn church_fst() -> Term {
+    church_project(true)
+}
+
+/// `λp (p λa λb b)`, which projects the second component of a Church pair.
+pub fn church_snd() -> Term {
+    church_project(false)
+}
+
+fn church_project(first: bool) -> Term {
+    let (label, index) = if first { ("a", 1) } else { ("b", 0) };
+    Term::lam(
+        NameIntro::new("p"),
+        Term::neu(
+            NameRef::new("p", 0),
+            vec![Term::lams_term(
+                vec![NameIntro::new("a"), NameIntro::new("b")],
+                Term::var(NameRef::new(label, index)),
+            )],
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        church::{
+            church_bool, church_fst, church_if, church_numeral, church_pair, church_snd,
+            decode_church, decode_church_bool,
+        },
+        interpretation::interpret,
+        syntax::{term_builder::*, to_builder, Env, Term},
+    };
+
+    #[test]
+    fn test_church_numeral() {
+        assert_eq!(format!("{}", church_numeral(0)), "λf λx x#0");
+        assert_eq!(format!("{}", church_numeral(2)), "λf λx (f#1 (f#1 x#0))");
+    }
+
+    #[test]
+    fn test_decode_church() {
+        for n in [0, 1, 7] {
+            let val = interpret(&Env::default(), &church_numeral(n)).unwrap();
+            assert_eq!(decode_church(&val), Ok(n));
+        }
+
+        // (def mul = λm λn λg (m (n g)) in (mul 3 4))
+        let term: Term = def(
+            "mul",
+            lam(
+                "m",
+                lam("n", lam("g", neu("m", vec![neu("n", vec![var("g")])]))),
+            ),
+            neu(
+                "mul",
+                vec![
+                    to_builder(&church_numeral(3)),
+                    to_builder(&church_numeral(4)),
+                ],
+            ),
+        )
+        .into();
+        let val = interpret(&Env::default(), &term).unwrap();
+        assert_eq!(decode_church(&val), Ok(12));
+
+        for term in [lam("x", var("x")), lam("f", lam("x", var("f")))] {
+            let val = interpret(&Env::default(), &term.into()).unwrap();
+            assert!(decode_church(&val).is_err());
+        }
+    }
+
+    #[test]
+    fn test_church_bool() {
+        for b in [true, false] {
+            let val = interpret(&Env::default(), &church_bool(b)).unwrap();
+            assert_eq!(decode_church_bool(&val), Ok(b));
+        }
+        let val = interpret(&Env::default(), &church_numeral(1)).unwrap();
+        assert!(decode_church_bool(&val).is_err());
+
+        // (if true 1 2) and (if false 1 2)
+        for (b, n) in [(true, 1), (false, 2)] {
+            let term: Term = def(
+                "if",
+                to_builder(&church_if()),
+                neu(
+                    "if",
+                    vec![
+                        to_builder(&church_bool(b)),
+                        to_builder(&church_numeral(1)),
+                        to_builder(&church_numeral(2)),
+                    ],
+                ),
+            )
+            .into();
+            let val = interpret(&Env::default(), &term).unwrap();
+            assert_eq!(decode_church(&val), Ok(n));
+        }
+    }
+
+    #[test]
+    fn test_church_pair() {
+        let pair = church_pair(church_numeral(3), church_bool(false));
+        for (project, projection) in [("fst", church_fst()), ("snd", church_snd())] {
+            let term: Term = def(
+                project,
+                to_builder(&projection),
+                def("p", to_builder(&pair), neu(project, vec![var("p")])),
+            )
+            .into();
+            let val = interpret(&Env::default(), &term).unwrap();
+            if project == "fst" {
+                assert_eq!(decode_church(&val), Ok(3));
+            } else {
+                assert_eq!(decode_church_bool(&val), Ok(false));
+            }
+        }
+    }
+}
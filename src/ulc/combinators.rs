@@ -0,0 +1,181 @@
+use super::syntax::{term_builder::*, Term};
+
+#[cfg(any(test, feature = "proptest"))]
+use super::church::{church_bool, church_fst, church_if, church_numeral, church_pair, church_snd};
+
+/// `I = λx x`, so that `I x = x`.
+pub fn i() -> Term {
+    lam("x", var("x")).into()
+}
+
+/// `K = λx λy x`, so that `K x y = x`.
+pub fn k() -> Term {
+    lams(&["x", "y"], var("x")).into()
+}
+
+/// `S = λx λy λz (x z (y z))`, so that `S x y z = x z (y z)`.
+pub fn s() -> Term {
+    lams(
+        &["x", "y", "z"],
+        neu("x", vec![var("z"), neu("y", vec![var("z")])]),
+    )
+    .into()
+}
+
+/// `B = λx λy λz (x (y z))`, so that `B x y z = x (y z)`.
+pub fn b() -> Term {
+    lams(&["x", "y", "z"], neu("x", vec![neu("y", vec![var("z")])])).into()
+}
+
+/// `C = λx λy λz (x z y)`, so that `C x y z = x z y`.
+pub fn c() -> Term {
+    lams(&["x", "y", "z"], neu("x", vec![var("z"), var("y")])).into()
+}
+
+/// `W = λx λy (x y y)`, so that `W x y = x y y`.
+pub fn w() -> Term {
+    lams(&["x", "y"], neu("x", vec![var("y"), var("y")])).into()
+}
+
+/// `ω = λx (x x)`, so that `ω x = x x`.
+pub fn omega() -> Term {
+    lam("x", neu("x", vec![var("x")])).into()
+}
+
+/// `Y = λf (def g = λx (f (x x)) in (g g))`, so that `Y f = f (Y f)`. Since a
+/// lambda can't be applied directly, the lambda that the usual
+/// `λf ((λx (f (x x))) (λx (f (x x))))` applies to itself is named `g`. Only
+/// normal-order interpretation finds the value of `Y f`.
+pub fn y() -> Term {
+    lam(
+        "f",
+        def(
+            "g",
+            lam("x", neu("f", vec![neu("x", vec![var("x")])])),
+            neu("g", vec![var("g")]),
+        ),
+    )
+    .into()
+}
+
+/// A strategy for closed terms that apply Church numerals, booleans, and pairs,
+/// their eliminators, and `S`, `K`, and `I` to each other. These are more
+/// likely than arbitrary terms to normalize to recognizable data, though some
+/// of them, like `S I I (S I I)`, diverge. Only available in tests and with the
+/// `proptest` feature.
+#[cfg(any(test, feature = "proptest"))]
+pub fn arb_encoded_term() -> impl proptest::strategy::Strategy<Value = Term> {
+    use proptest::prelude::*;
+    let leaf = prop_oneof![
+        (0u64..4).prop_map(church_numeral),
+        any::<bool>().prop_map(church_bool),
+        Just(church_if()),
+        Just(church_fst()),
+        Just(church_snd()),
+        Just(s()),
+        Just(k()),
+        Just(i()),
+    ];
+    leaf.prop_recursive(4, 24, 2, |inner| {
+        prop_oneof![
+            (inner.clone(), inner.clone()).prop_map(|(func, arg)| Term::app(func, arg)),
+            (inner.clone(), inner).prop_map(|(a, b)| church_pair(a, b)),
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::ulc::{
+        combinators::{arb_encoded_term, b, c, i, k, omega, s, w, y},
+        interpretation::{
+            evaluate_with_fuel, interpret, interpret_normal_order, readback, readback_normal_order,
+            InterpretError,
+        },
+        syntax::{alpha_eq, term_builder::*, to_builder, Env, Term, TermBuilder},
+    };
+
+    /// Binds each combinator to its name in `body`.
+    fn with_combinators(body: TermBuilder) -> Term {
+        [
+            ("i", i()),
+            ("k", k()),
+            ("s", s()),
+            ("b", b()),
+            ("c", c()),
+            ("w", w()),
+            ("omega", omega()),
+            ("y", y()),
+        ]
+        .into_iter()
+        .rev()
+        .fold(body, |body, (name, term)| {
+            def(name, to_builder(&term), body)
+        })
+        .into()
+    }
+
+    fn normalize(body: TermBuilder) -> Term {
+        readback(&interpret(&Env::default(), &with_combinators(body)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_combinators() {
+        let abc = |body| lams(&["p", "q", "r"], body);
+        assert!(alpha_eq(
+            &normalize(abc(neu("i", vec![var("p")]))),
+            &abc(var("p")).into()
+        ));
+        assert!(alpha_eq(
+            &normalize(abc(neu("k", vec![var("p"), var("q")]))),
+            &abc(var("p")).into()
+        ));
+        assert!(alpha_eq(
+            &normalize(abc(neu("s", vec![var("p"), var("q"), var("r")]))),
+            &abc(neu("p", vec![var("r"), neu("q", vec![var("r")])])).into()
+        ));
+        assert!(alpha_eq(
+            &normalize(abc(neu("b", vec![var("p"), var("q"), var("r")]))),
+            &abc(neu("p", vec![neu("q", vec![var("r")])])).into()
+        ));
+        assert!(alpha_eq(
+            &normalize(abc(neu("c", vec![var("p"), var("q"), var("r")]))),
+            &abc(neu("p", vec![var("r"), var("q")])).into()
+        ));
+        assert!(alpha_eq(
+            &normalize(abc(neu("w", vec![var("p"), var("q")]))),
+            &abc(neu("p", vec![var("q"), var("q")])).into()
+        ));
+        assert!(alpha_eq(
+            &normalize(abc(neu("omega", vec![var("p")]))),
+            &abc(neu("p", vec![var("p")])).into()
+        ));
+        // S K K = I
+        assert!(alpha_eq(
+            &normalize(neu("s", vec![var("k"), var("k")])),
+            &i()
+        ));
+    }
+
+    #[test]
+    fn test_y() {
+        // Y (λr λx x) = (λr λx x) (Y (λr λx x)) = λx x
+        let term = with_combinators(neu("y", vec![lam("r", lam("x", var("x")))]));
+        let val = interpret_normal_order(&Env::default(), &term).unwrap();
+        assert!(alpha_eq(&readback_normal_order(&val).unwrap(), &i()));
+    }
+
+    proptest! {
+        #[test]
+        fn test_arb_encoded_term_evaluates(term in arb_encoded_term()) {
+            // either a normal form is found or the term is cut off as
+            // divergent, but it never fails in any other way
+            match evaluate_with_fuel(&term, 100) {
+                Ok(_) | Err(InterpretError::OutOfFuel) => {}
+                Err(err) => prop_assert!(false, "{} failed with {}", term, err),
+            }
+        }
+    }
+}
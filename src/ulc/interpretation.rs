@@ -1,172 +1,2827 @@
-use super::syntax::{Env, Term, Val};
+use core::fmt;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    rc::Rc,
+};
 
-pub fn interpret(env: &Env, term: &Term) -> Result<Val, String> {
+use super::{
+    lint::LintWarning,
+    syntax::{
+        alpha_eq, free_vars, reindex, shadowed_names, shift, subst, Env, NameIntro, NameRef, Term,
+        Val,
+    },
+};
+
+// ================================================================================
+/// ## InterpretError
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum InterpretError {
+    /// The environment has no binding at the variable's index.
+    UnboundVariable { label: String, index: usize },
+    /// The environment's binding at the variable's index has a different label.
+    LabelIndexMismatch {
+        index: usize,
+        expected: String,
+        found: String,
+    },
+    /// A binder shadows an enclosing binder (see [`interpret_no_shadow`]).
+    ShadowingForbidden { name: NameIntro, depth: usize },
+    /// A neutral variable was read back outside of the binder that introduced
+    /// it.
+    UnboundNeutral {
+        label: String,
+        level: usize,
+        depth: usize,
+    },
+    /// A definition of a program was not interpreted because an earlier
+    /// definition failed.
+    SkippedDefinition { name: NameIntro },
+    /// The interpreter ran out of fuel (see [`interpret_with_fuel`]).
+    OutOfFuel,
+    /// The number of arguments waiting to be applied grew by
+    /// `observed_growth` without ever shrinking (see
+    /// [`interpret_with_growth_guard`]).
+    LikelyDivergent { observed_growth: usize },
+    /// The interpretation of a subterm was nested more than `limit` deep (see
+    /// [`interpret_with_depth_limit`]).
+    StackDepthExceeded { limit: usize },
+    /// A [`Term::Hole`] was interpreted.
+    ReachedHole,
+}
+
+impl Display for InterpretError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            InterpretError::UnboundVariable { label, index } => write!(
+                f,
+                "environment doesn't have binding at index `{}` of name `{}`",
+                index, label
+            ),
+            InterpretError::LabelIndexMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "environment's binding at index `{}` was expected to have the name `{}` but it actually has the name `{}`",
+                index, expected, found
+            ),
+            InterpretError::ShadowingForbidden { name, depth } => write!(
+                f,
+                "the binder `{}` at depth `{}` shadows an enclosing binder of the same name",
+                name, depth
+            ),
+            InterpretError::UnboundNeutral {
+                label,
+                level,
+                depth,
+            } => write!(
+                f,
+                "the neutral variable `{}` at level `{}` is not bound at depth `{}`",
+                label, level, depth
+            ),
+            InterpretError::SkippedDefinition { name } => write!(
+                f,
+                "definition `{}` was not interpreted because an earlier definition failed",
+                name
+            ),
+            InterpretError::OutOfFuel => write!(f, "the interpreter ran out of fuel"),
+            InterpretError::LikelyDivergent { observed_growth } => write!(
+                f,
+                "the interpretation is likely divergent since the number of pending arguments grew by `{}`",
+                observed_growth
+            ),
+            InterpretError::StackDepthExceeded { limit } => write!(
+                f,
+                "the interpretation of a subterm was nested more than `{}` deep",
+                limit
+            ),
+            InterpretError::ReachedHole => write!(
+                f,
+                "the hole `?` was interpreted, but it stands for an unknown subterm"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InterpretError {}
+
+// ================================================================================
+/// ## TraceEvent
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraceEvent {
+    /// The name of the lambda that was applied, or `None` if it was applied
+    /// directly, as the function of an `App`.
+    pub applicant: Option<NameRef>,
+    /// The argument that the lambda was applied to.
+    pub argument: Val,
+    /// The environment that the lambda's body was interpreted in, which binds
+    /// its parameter to `argument`.
+    pub env: Env,
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self.applicant {
+            Some(applicant) => write!(f, "({} {}) in {}", applicant, self.argument, self.env),
+            None => write!(f, "(<lambda> {}) in {}", self.argument, self.env),
+        }
+    }
+}
+
+// ================================================================================
+/// ## Derivation
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Derivation {
+    /// The judgment `env ⊢ term ⇓ val` that is derived.
+    pub env: Env,
+    pub term: Term,
+    pub val: Val,
+    /// The derivations of the interpretations that the judgment was derived
+    /// from, in the order they were made.
+    pub premises: Vec<Derivation>,
+}
+
+impl Derivation {
+    fn fmt_at(&self, f: &mut Formatter, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{} ⊢ {} ⇓ {}",
+            "",
+            self.env,
+            self.term,
+            self.val,
+            indent = 2 * depth
+        )?;
+        for premise in &self.premises {
+            premise.fmt_at(f, depth + 1)?
+        }
+        Ok(())
+    }
+}
+
+/// Renders the judgment on the first line, followed by its premises, each
+/// indented under it.
+impl Display for Derivation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.fmt_at(f, 0)
+    }
+}
+
+pub fn interpret(env: &Env, term: &Term) -> Result<Val, InterpretError> {
+    Interpreter::default().interpret(env, term)
+}
+
+/// Interprets `term` like [`interpret`], but fails with
+/// [`InterpretError::OutOfFuel`] instead of performing more than `fuel`
+/// beta-reductions (applications of a lambda to an argument).
+pub fn interpret_with_fuel(env: &Env, term: &Term, fuel: usize) -> Result<Val, InterpretError> {
+    Interpreter {
+        fuel: Some(fuel),
+        ..Interpreter::default()
+    }
+    .interpret(env, term)
+}
+
+/// Interprets `term` like [`interpret`], and also returns the number of
+/// beta-reductions it took, i.e. the number of arguments that lambdas were
+/// applied to.
+pub fn interpret_counting(env: &Env, term: &Term) -> Result<(Val, usize), InterpretError> {
+    let mut interpreter = Interpreter::default();
+    let val = interpreter.interpret(env, term)?;
+    Ok((val, interpreter.betas))
+}
+
+/// Interprets `term` like [`interpret`], but also records each beta-reduction
+/// in order. The trace covers every reduction made before an error, if any.
+pub fn interpret_traced(env: &Env, term: &Term) -> (Result<Val, InterpretError>, Vec<TraceEvent>) {
+    let mut interpreter = Interpreter {
+        trace: Some(vec![]),
+        ..Interpreter::default()
+    };
+    let result = interpreter.interpret(env, term);
+    (result, interpreter.trace.unwrap_or_default())
+}
+
+/// Interprets `term` like [`interpret`], and also returns the derivation of its
+/// value, which has a premise for each interpretation of a subterm, of a
+/// lambda's body, or of a thunk that was needed along the way.
+pub fn interpret_with_derivation(
+    env: &Env,
+    term: &Term,
+) -> Result<(Val, Derivation), InterpretError> {
+    let mut interpreter = Interpreter {
+        derivations: Some(vec![vec![]]),
+        ..Interpreter::default()
+    };
+    let val = interpreter.interpret(env, term)?;
+    let derivation = interpreter.derivations.unwrap().remove(0).remove(0);
+    Ok((val, derivation))
+}
+
+/// Interprets `term` like [`interpret`], but fails with
+/// [`InterpretError::LikelyDivergent`] once the number of arguments waiting to
+/// be applied has grown by more than `growth_limit` over successive
+/// beta-reductions without shrinking in between. Such growth means that the
+/// term being reduced keeps getting bigger, as in `((λx (x x x)) λx (x x x))`,
+/// which is likely to go on forever.
+pub fn interpret_with_growth_guard(
+    env: &Env,
+    term: &Term,
+    growth_limit: usize,
+) -> Result<Val, InterpretError> {
+    Interpreter {
+        growth_limit: Some(growth_limit),
+        ..Interpreter::default()
+    }
+    .interpret(env, term)
+}
+
+/// Interprets `term` like [`interpret`], but fails with
+/// [`InterpretError::StackDepthExceeded`] instead of nesting the interpretation
+/// of subterms more than `limit` deep. Each nested interpretation uses native
+/// stack, so this turns what would be a stack overflow into an error, as long
+/// as `limit` is small enough for the stack.
+pub fn interpret_with_depth_limit(
+    env: &Env,
+    term: &Term,
+    limit: usize,
+) -> Result<Val, InterpretError> {
+    Interpreter {
+        depth_limit: Some(limit),
+        ..Interpreter::default()
+    }
+    .interpret(env, term)
+}
+
+/// Interprets `term` like [`interpret`], but remembers the value of each
+/// subterm in each environment it is interpreted in, and reuses it whenever
+/// that subterm is interpreted in the same environment again. Environments are
+/// told apart by identity, so looking one up takes constant time, but an equal
+/// environment that was built separately doesn't share its values. Subterms
+/// are compared by content, which takes time proportional to their size, so
+/// this only pays off for terms that repeat expensive computations.
+pub fn interpret_memoized(env: &Env, term: &Term) -> Result<Val, InterpretError> {
+    Interpreter {
+        memo: Some(HashMap::new()),
+        ..Interpreter::default()
+    }
+    .interpret(env, term)
+}
+
+/// Interprets `term` like [`interpret`], but where a variable isn't bound in
+/// `env`, evaluates it to a neutral rather than failing, so that applying it
+/// collects its arguments. The applicant of such a neutral is the variable's
+/// index in the context that `env` is in, rather than a level.
+pub fn interpret_open(env: &Env, term: &Term) -> Result<Val, InterpretError> {
+    Interpreter {
+        open: true,
+        ..Interpreter::default()
+    }
+    .interpret(env, term)
+}
+
+/// Interprets `term` like [`interpret`], but in normal order: the arguments of
+/// an application and the binding of a `def` are not interpreted until they
+/// are needed, so they are bound as [`Val::Thunk`]s. This finds the value of
+/// terms like `((λx λy y) ω)`, whose argument `ω` has no value.
+pub fn interpret_normal_order(env: &Env, term: &Term) -> Result<Val, InterpretError> {
+    Interpreter {
+        normal_order: true,
+        ..Interpreter::default()
+    }
+    .interpret(env, term)
+}
+
+/// Reduces `term` to weak head normal form: a lambda, whose body is left
+/// unevaluated, or a neutral term stuck on a free variable, whose arguments
+/// are left as thunks. This is the same reduction as [`interpret_normal_order`],
+/// which never looks under a binder either; to fully normalize the result, read
+/// it back with [`readback_normal_order`]. Unlike [`interpret`], the arguments
+/// of an application are not evaluated, so the head can be found even when an
+/// argument has no value.
+pub fn whnf(env: &Env, term: &Term) -> Result<Val, InterpretError> {
+    interpret_normal_order(env, term)
+}
+
+/// Interprets `term` like [`interpret`], but also warns about every lambda
+/// that captures an environment with more than `max_capture` bindings, since
+/// that usually means values are being kept alive for longer than needed.
+pub fn interpret_with_capture_warnings(
+    env: &Env,
+    term: &Term,
+    max_capture: usize,
+) -> (Result<Val, InterpretError>, Vec<LintWarning>) {
+    let mut interpreter = Interpreter {
+        max_capture: Some(max_capture),
+        ..Interpreter::default()
+    };
+    let result = interpreter.interpret(env, term);
+    (result, interpreter.warnings)
+}
+
+/// Interprets `term` like [`interpret`], but first rejects it if any of its
+/// binders shadows an enclosing binder of the term.
+pub fn interpret_no_shadow(env: &Env, term: &Term) -> Result<Val, InterpretError> {
+    if let Some((name, depth)) = shadowed_names(term).into_iter().next() {
+        return Err(InterpretError::ShadowingForbidden { name, depth });
+    }
+    interpret(env, term)
+}
+
+/// Applies `applicant` to `arguments` in order, like the interpretation of an
+/// application whose applicant and arguments have already been interpreted.
+/// Each argument is bound to the parameter of the lambda that the previous
+/// application resulted in, extending its closure, and its body is interpreted
+/// in that closure. Once the result is a neutral, the remaining arguments are
+/// appended to it.
+pub fn apply(applicant: &Val, arguments: Vec<Box<Val>>) -> Result<Val, InterpretError> {
+    Interpreter::default().apply(
+        None,
+        applicant,
+        arguments.into_iter().map(|argument| *argument).collect(),
+    )
+}
+
+/// The beta-normal form of `term` in `env`, found by interpreting it and then
+/// reading back its value, which reduces under each lambda by interpreting its
+/// body with a neutral variable bound to its parameter (see [`readback`]). A
+/// variable of `term` bound in `env` to a neutral at index `i` should have
+/// level `env.len() - 1 - i`, so that it is read back as a reference to that
+/// binding.
+pub fn normalize(env: &Env, term: &Term) -> Result<Term, InterpretError> {
+    let mut interpreter = Interpreter::default();
+    let val = interpreter.interpret(env, term)?;
+    interpreter.readback_at(env.len(), &val)
+}
+
+/// The normal form of the closed `term`, found by interpreting it and reading
+/// back its value (see [`normalize`]). Like [`interpret`], this doesn't
+/// terminate if `term` has no normal form; see [`evaluate_with_fuel`].
+pub fn evaluate(term: &Term) -> Result<Term, InterpretError> {
+    normalize(&Env::default(), term)
+}
+
+/// Evaluates the closed `term` like [`evaluate`], but fails with
+/// [`InterpretError::OutOfFuel`] instead of performing more than `fuel`
+/// beta-reductions, counting those taken to read back its value.
+pub fn evaluate_with_fuel(term: &Term, fuel: usize) -> Result<Term, InterpretError> {
+    let mut interpreter = Interpreter {
+        fuel: Some(fuel),
+        ..Interpreter::default()
+    };
+    let val = interpreter.interpret(&Env::default(), term)?;
+    interpreter.readback_at(0, &val)
+}
+
+/// Reads a value back into a `Term` in normal form. The body of a lambda is
+/// normalized by interpreting it in its closure extended with a neutral
+/// variable for the lambda's parameter, and then reading back the result.
+pub fn readback(val: &Val) -> Result<Term, InterpretError> {
+    Interpreter::default().readback_at(0, val)
+}
+
+/// Reads a value back like [`readback`], but interprets the bodies of lambdas
+/// in normal order (see [`interpret_normal_order`]).
+pub fn readback_normal_order(val: &Val) -> Result<Term, InterpretError> {
+    Interpreter {
+        normal_order: true,
+        ..Interpreter::default()
+    }
+    .readback_at(0, val)
+}
+
+/// Whether the closed terms `a` and `b` reduce to a common term. Since the
+/// untyped lambda calculus is confluent, this is the case exactly when they
+/// have alpha-equivalent normal forms, which are found in normal order with at
+/// most `fuel` beta-reductions each. Fails with [`InterpretError::OutOfFuel`]
+/// if either doesn't reach its normal form within `fuel`.
+pub fn joinable(a: &Term, b: &Term, fuel: usize) -> Result<bool, InterpretError> {
+    Ok(alpha_eq(
+        &normal_form_within(a, fuel)?,
+        &normal_form_within(b, fuel)?,
+    ))
+}
+
+/// Whether `a` and `b` are definitionally equal, i.e. their values read back to
+/// alpha-equivalent normal forms. Each variable that is free in either term is
+/// bound to a neutral, so `(def id = λx x in (id y))` is equal to `y`.
+pub fn def_eq(a: &Term, b: &Term) -> Result<bool, InterpretError> {
+    let depth = free_vars(a)
+        .into_iter()
+        .chain(free_vars(b))
+        .map(|name| name.index() + 1)
+        .max()
+        .unwrap_or(0);
+    Ok(alpha_eq(
+        &open_normal_form(a, depth)?,
+        &open_normal_form(b, depth)?,
+    ))
+}
+
+/// The normal form of `term` under `depth` binders, found by binding each of
+/// its free variables to a neutral.
+fn open_normal_form(term: &Term, depth: usize) -> Result<Term, InterpretError> {
+    let mut labels = vec!["_".to_string(); depth];
+    for name in free_vars(term) {
+        labels[name.index()] = name.label().to_string()
+    }
+    let env = labels
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| {
+            let variable = Val::neutral(NameRef::new(&label, depth - 1 - index), vec![]);
+            (NameIntro::new(&label), variable)
+        })
+        .collect();
+    normalize(&env, term)
+}
+
+/// The normal form of the closed `term`, found in normal order with at most
+/// `fuel` beta-reductions.
+fn normal_form_within(term: &Term, fuel: usize) -> Result<Term, InterpretError> {
+    let mut interpreter = Interpreter {
+        fuel: Some(fuel),
+        normal_order: true,
+        ..Interpreter::default()
+    };
+    let val = interpreter.interpret(&Env::default(), term)?;
+    interpreter.readback_at(0, &val)
+}
+
+/// Normalizes each of the closed `terms` like [`joinable`] does, with at most
+/// `fuel` beta-reductions each. With the `rayon` feature, the terms are
+/// normalized in parallel. The results are in the same order as `terms`.
+pub fn normalize_batch(terms: Vec<Term>, fuel: usize) -> Vec<Result<Term, InterpretError>> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        terms
+            .par_iter()
+            .map(|term| normal_form_within(term, fuel))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        terms
+            .iter()
+            .map(|term| normal_form_within(term, fuel))
+            .collect()
+    }
+}
+
+/// The largest argument that [`find_distinguishing_argument`] tries.
+const MAX_WITNESS_SIZE: usize = 6;
+
+/// Finds a closed argument that the closed functions `a` and `b` map to
+/// different normal forms, as a witness that they aren't equivalent. The
+/// candidates are all the closed terms in normal form of up to
+/// `MAX_WITNESS_SIZE` nodes, smallest first, and each application is
+/// normalized with at most `fuel` beta-reductions. Returns `None` if `a` and
+/// `b` have the same normal form, or if no candidate is found to distinguish
+/// them; a candidate for which either application runs out of fuel is skipped.
+pub fn find_distinguishing_argument(a: &Term, b: &Term, fuel: usize) -> Option<Term> {
+    if joinable(a, b, fuel) == Ok(true) {
+        return None;
+    }
+    let apply = |function: &Term, argument: &Term| {
+        let f = NameIntro::new("f");
+        normal_form_within(
+            &Term::def(
+                f.clone(),
+                function.clone(),
+                Term::neu(NameRef::new(&f.label, 0), vec![argument.clone()]),
+            ),
+            fuel,
+        )
+    };
+    (1..=MAX_WITNESS_SIZE)
+        .flat_map(|size| normal_terms(size, 0))
+        .find(|argument| match (apply(a, argument), apply(b, argument)) {
+            (Ok(a), Ok(b)) => !alpha_eq(&a, &b),
+            _ => false,
+        })
+}
+
+/// All the terms in normal form with `size` nodes (see [`Term::size`]) and no
+/// `def`s, in a context of `depth` binders.
+fn normal_terms(size: usize, depth: usize) -> Vec<Term> {
+    let label = |level: usize| format!("x{}", level);
+    let mut terms = vec![];
+    if size == 1 {
+        terms.extend(
+            (0..depth).map(|index| Term::var(NameRef::new(&label(depth - 1 - index), index))),
+        );
+    }
+    if size >= 2 {
+        terms.extend(
+            normal_terms(size - 1, depth + 1)
+                .into_iter()
+                .map(|body| Term::lam(NameIntro::new(&label(depth)), body)),
+        );
+        for arguments in normal_argument_lists(size - 1, depth) {
+            terms.extend((0..depth).map(|index| {
+                Term::neu(
+                    NameRef::new(&label(depth - 1 - index), index),
+                    arguments.clone(),
+                )
+            }))
+        }
+    }
+    terms
+}
+
+/// All the non-empty lists of terms in normal form whose sizes add up to
+/// `size`, in a context of `depth` binders.
+fn normal_argument_lists(size: usize, depth: usize) -> Vec<Vec<Term>> {
+    let mut lists = vec![];
+    for first_size in 1..=size {
+        for first in normal_terms(first_size, depth) {
+            if first_size == size {
+                lists.push(vec![first.clone()])
+            }
+            for rest in normal_argument_lists(size - first_size, depth) {
+                lists.push([vec![first.clone()], rest].concat())
+            }
+        }
+    }
+    lists
+}
+
+/// Whether `term` has no application that could be reduced, without
+/// interpreting it. An application can be reduced if its applicant is bound by
+/// a `def` to a lambda, possibly through other `def`s that rename it.
+/// Applications of lambda parameters and of free variables are stuck, and so
+/// are normal. A `def` is not itself considered reducible, and neither is
+/// anything within a frozen term.
+pub fn is_normal_form(term: &Term) -> bool {
+    is_normal_form_in(&mut vec![], term)
+}
+
+/// Whether a variable is known to be bound to a lambda.
+#[derive(Clone, Copy, PartialEq)]
+enum Binding {
+    Lam,
+    Stuck,
+}
+
+fn lookup_binding(ctx: &[Binding], name: &NameRef) -> Binding {
+    ctx.len()
+        .checked_sub(name.index() + 1)
+        .map_or(Binding::Stuck, |level| ctx[level])
+}
+
+/// What `term` is known to evaluate to in `ctx`.
+fn resolve_binding(ctx: &mut Vec<Binding>, term: &Term) -> Binding {
+    match term {
+        Term::Lam { .. } => Binding::Lam,
+        Term::Var(name) => lookup_binding(ctx, name),
+        Term::Neu { .. } | Term::App { .. } | Term::Hole => Binding::Stuck,
+        Term::Def {
+            intro: _,
+            binding,
+            body,
+        } => {
+            let binding = resolve_binding(ctx, binding);
+            ctx.push(binding);
+            let binding = resolve_binding(ctx, body);
+            ctx.pop();
+            binding
+        }
+        // a frozen lambda is thawed when it is applied
+        Term::Freeze(term) | Term::Force(term) => resolve_binding(ctx, term),
+    }
+}
+
+/// `ctx` holds the binding of each enclosing binder, innermost last.
+fn is_normal_form_in(ctx: &mut Vec<Binding>, term: &Term) -> bool {
     match term {
-        Term::Lam { intro: name, body } => {
-            Ok(Val::lam(env.clone(), name.clone(), body.as_ref().clone()))
+        Term::Lam { intro: _, body } => {
+            ctx.push(Binding::Stuck);
+            let normal = is_normal_form_in(ctx, body);
+            ctx.pop();
+            normal
         }
+        Term::Var(_) => true,
         Term::Neu {
             applicant,
             arguments,
         } => {
-            let arguments = arguments
-                .iter()
-                .map(|arg| {
-                    let val = interpret(env, arg)?;
-                    Ok(Box::new(val))
-                })
-                .collect::<Result<Vec<Box<Val>>, String>>()?;
-            let applicant = env.lookup(applicant)?;
-            apply(&applicant, arguments)
+            lookup_binding(ctx, applicant) == Binding::Stuck
+                && arguments
+                    .iter()
+                    .all(|argument| is_normal_form_in(ctx, argument))
+        }
+        Term::Def {
+            intro: _,
+            binding: def_binding,
+            body,
+        } => {
+            if !is_normal_form_in(ctx, def_binding) {
+                return false;
+            }
+            let binding = resolve_binding(ctx, def_binding);
+            ctx.push(binding);
+            let normal = is_normal_form_in(ctx, body);
+            ctx.pop();
+            normal
+        }
+        Term::App { func, arg } => {
+            resolve_binding(ctx, func) == Binding::Stuck
+                && is_normal_form_in(ctx, func)
+                && is_normal_form_in(ctx, arg)
+        }
+        Term::Freeze(_) | Term::Hole => true,
+        Term::Force(term) => is_normal_form_in(ctx, term),
+    }
+}
+
+/// An environment that binds each free variable of `term` to a neutral with its
+/// level, along with the number of bindings in it.
+fn free_var_env(term: &Term) -> (Env, usize) {
+    let free = free_vars(term);
+    let len = free.iter().map(|name| name.index() + 1).max().unwrap_or(0);
+    let label = |index: usize| {
+        free.iter()
+            .find(|name| name.index() == index)
+            .map_or("_".to_string(), |name| name.label().to_string())
+    };
+    let env = (0..len).fold(Env::default(), |env, level| {
+        let label = label(len - 1 - level);
+        env.extend(
+            NameIntro::new(&label),
+            Box::new(Val::neutral(NameRef::new(&label, level), vec![])),
+        )
+    });
+    (env, len)
+}
+
+/// The variable that the weak head normal form of `term` is stuck on, or
+/// `None` if it is a lambda. Each free variable of `term` is interpreted as a
+/// neutral, so the head is one of them, with its index referring to the
+/// context that `term` is in. The head is found by [`whnf`], which doesn't
+/// evaluate arguments, so `(x (ω ω))` is stuck on `x`; a frozen term is forced
+/// to find its head. This doesn't terminate if `term` has no weak head normal
+/// form.
+pub fn head_variable(term: &Term) -> Result<Option<NameRef>, InterpretError> {
+    let (env, len) = free_var_env(term);
+    let mut val = whnf(&env, term)?;
+    loop {
+        match val {
+            Val::Neutral { applicant, .. } => {
+                return Ok(Some(NameRef::new(
+                    applicant.label(),
+                    len - 1 - applicant.index(),
+                )))
+            }
+            Val::Lam { .. } => return Ok(None),
+            Val::Thunk { term, closure } | Val::Frozen { term, closure } => {
+                val = whnf(&closure, &term)?
+            }
+        }
+    }
+}
+
+/// The Böhm tree of `term` cut off at `depth`: the head normal form
+/// `λx ... (h a ...)` of `term`, with each argument `a` replaced by its own
+/// prefix of depth `depth - 1`, and [`Term::Hole`] in place of a prefix of
+/// depth `0`. Free variables of `term` stay free, like in [`head_variable`].
+/// Arguments below `depth` are never evaluated, so `(x (ω ω))` has the prefix
+/// `(x ?)` of depth `1`, but this doesn't terminate if a subterm within `depth`
+/// has no head normal form.
+pub fn bohm_prefix(term: &Term, depth: usize) -> Result<Term, InterpretError> {
+    let (env, len) = free_var_env(term);
+    let mut interpreter = Interpreter {
+        normal_order: true,
+        ..Interpreter::default()
+    };
+    let val = interpreter.interpret(&env, term)?;
+    interpreter.readback_prefix(len, &val, depth)
+}
+
+/// Performs a single outermost reduction of `term` in `env`, returning the
+/// reduct along with the environment it is to be interpreted in, or `None` if
+/// `term` is a value, a frozen term, a stuck neutral, or headed by a variable
+/// bound to a [`Val::Thunk`] or a [`Val::Frozen`]. A step is one of:
+///
+/// - `def x = e in b` takes a step in `e` if it can, and otherwise interprets
+///   `e` and continues with `b`.
+/// - `⟨force⟩ e` continues with `e`.
+/// - `x` continues with the lambda that `x` is bound to.
+/// - `(f a ...)`, where `f` is bound to `λ[Env]x b`, takes a step in `a` if it
+///   can, and otherwise interprets `a` and continues with `b`. Only the first
+///   argument is consumed: any remaining arguments are kept, uninterpreted, in
+///   a continuation `k = λr (r ...)` closed over `env`, and the step continues
+///   with `def r = b in (k r)`.
+/// - `(e a)`, where `e` isn't a name, continues with `def f = e in (f a)`.
+///
+/// A step in a binding or an argument `e` that reduces it to `e'` in `env'`
+/// continues with `(k e')` in `env'`, where the continuation `k` is closed
+/// over `env` and binds the value of `e` in the rest of the term. Variables,
+/// lambdas, and frozen terms count as values, as do terms that no step
+/// applies to, which are interpreted as a whole. The binders `f`, `k`, and
+/// `r` that a step introduces are only labelled so for display: they are
+/// referred to by index, so they never capture a variable of the same name.
+pub fn step(env: &Env, term: &Term) -> Result<Option<(Env, Term)>, InterpretError> {
+    match term {
+        Term::Lam { .. } | Term::Freeze(_) => Ok(None),
+        Term::Hole => Err(InterpretError::ReachedHole),
+        Term::Var(name) => match *env.lookup(name)? {
+            Val::Lam {
+                closure,
+                intro,
+                body,
+            } => Ok(Some((
+                *closure,
+                Term::lam(intro, Rc::unwrap_or_clone(body)),
+            ))),
+            Val::Neutral { .. } | Val::Thunk { .. } | Val::Frozen { .. } => Ok(None),
+        },
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let (closure, intro, body) = match *env.lookup(applicant)? {
+                Val::Lam {
+                    closure,
+                    intro,
+                    body,
+                } => (closure, intro, body),
+                Val::Neutral { .. } | Val::Thunk { .. } | Val::Frozen { .. } => return Ok(None),
+            };
+            let Some((argument, arguments)) = arguments.split_first() else {
+                return Ok(Some((
+                    *closure,
+                    Term::lam(intro, Rc::unwrap_or_clone(body)),
+                )));
+            };
+            let rest = Term::neu(
+                NameRef::new(applicant.label(), applicant.index() + 1),
+                std::iter::once(Term::var(NameRef::new(&intro.label, 0)))
+                    .chain(arguments.iter().map(|argument| shift(argument, 0, 1)))
+                    .collect(),
+            );
+            if let Some(reduct) = step_binding(env, argument, &intro, &rest)? {
+                return Ok(Some(reduct));
+            }
+            let argument = interpret(env, argument)?;
+            let env_body = closure.extend(intro, Box::new(argument));
+            if arguments.is_empty() {
+                return Ok(Some((env_body, Rc::unwrap_or_clone(body))));
+            }
+            let continuation = Val::lam(
+                env.clone(),
+                NameIntro::new("r"),
+                Term::neu(
+                    NameRef::new("r", 0),
+                    arguments
+                        .iter()
+                        .map(|argument| shift(argument, 0, 1))
+                        .collect(),
+                ),
+            );
+            Ok(Some((
+                env_body.extend(NameIntro::new("k"), Box::new(continuation)),
+                Term::def(
+                    NameIntro::new("r"),
+                    shift(&body, 0, 1),
+                    Term::neu(NameRef::new("k", 1), vec![Term::var(NameRef::new("r", 0))]),
+                ),
+            )))
         }
         Term::Def {
-            intro: name,
+            intro,
             binding,
             body,
         } => {
+            if let Some(reduct) = step_binding(env, binding, intro, body)? {
+                return Ok(Some(reduct));
+            }
             let binding = interpret(env, binding)?;
-            let env = env.extend(name.clone(), Box::new(binding));
-            interpret(&env, body)
+            Ok(Some((
+                env.extend(intro.clone(), Box::new(binding)),
+                body.as_ref().clone(),
+            )))
+        }
+        Term::App { func, arg } => Ok(Some((env.clone(), name_function(func, arg)))),
+        Term::Force(term) => Ok(Some((env.clone(), term.as_ref().clone()))),
+    }
+}
+
+/// Takes a step in `term`, which is to be bound to `intro` in `rest`, unless it
+/// is a value. The reduct `e'` in `env'` continues with `(k e')` in `env'`
+/// extended by `k = λ[env]intro rest`.
+fn step_binding(
+    env: &Env,
+    term: &Term,
+    intro: &NameIntro,
+    rest: &Term,
+) -> Result<Option<(Env, Term)>, InterpretError> {
+    if let Term::Var(_) = term {
+        return Ok(None);
+    }
+    let Some((next_env, next_term)) = step(env, term)? else {
+        return Ok(None);
+    };
+    let continuation = Val::lam(env.clone(), intro.clone(), rest.clone());
+    Ok(Some((
+        next_env.extend(NameIntro::new("k"), Box::new(continuation)),
+        Term::neu(NameRef::new("k", 0), vec![shift(&next_term, 0, 1)]),
+    )))
+}
+
+/// `def f = <func> in (f <arg>)`, which turns an `App` into a `Neu`.
+fn name_function(func: &Term, arg: &Term) -> Term {
+    Term::def(
+        NameIntro::new("f"),
+        func.clone(),
+        Term::neu(NameRef::new("f", 0), vec![shift(arg, 0, 1)]),
+    )
+}
+
+/// Narrates the reduction of `term` by [`step`], one sentence per step, until
+/// no step applies or `max_steps` steps have been taken. Each intermediate term
+/// is printed by its `Display` impl, so the variables it refers to are those of
+/// the environment accumulated by the earlier steps.
+pub fn explain(term: &Term, max_steps: usize) -> String {
+    let mut env = Env::default();
+    let mut term = term.clone();
+    let mut lines = vec![format!("Start with {}.", term)];
+    for _ in 0..max_steps {
+        match step(&env, &term) {
+            Ok(Some((next_env, next_term))) => {
+                lines.push(format!(
+                    "{}, giving {}.",
+                    explain_step(&env, &term),
+                    next_term
+                ));
+                (env, term) = (next_env, next_term);
+            }
+            Ok(None) => {
+                lines.push(match term {
+                    Term::Lam { .. } => format!("{} is a function, so evaluation is done.", term),
+                    _ => format!(
+                        "{} is stuck on a free variable, so evaluation is done.",
+                        term
+                    ),
+                });
+                return lines.join("\n");
+            }
+            Err(err) => {
+                lines.push(format!("Evaluation fails: {}.", err));
+                return lines.join("\n");
+            }
+        }
+    }
+    lines.push(format!("Stopped after {} steps.", max_steps));
+    lines.join("\n")
+}
+
+/// Describes the step that [`step`] takes from `term` in `env`.
+fn explain_step(env: &Env, term: &Term) -> String {
+    match term {
+        Term::Def { intro, binding, .. } => {
+            if steps(env, binding) {
+                return format!("Reduce {} before defining {}", binding, intro);
+            }
+            format!("Define {} to be {}", intro, binding)
+        }
+        Term::Var(name) => format!("Look up {}", name),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let function = match env.lookup(applicant).as_deref() {
+                Ok(Val::Lam { intro, body, .. }) => Term::lam(intro.clone(), body.as_ref().clone()),
+                _ => return format!("Reduce {}", term),
+            };
+            let Some((argument, arguments)) = arguments.split_first() else {
+                return format!("Look up {}", applicant);
+            };
+            let Term::Lam { intro, .. } = &function else {
+                unreachable!()
+            };
+            if steps(env, argument) {
+                return format!(
+                    "Reduce the argument {} before applying {}",
+                    argument, function
+                );
+            }
+            let mut sentence = format!(
+                "Apply the function {} to the argument {}, substituting {} for {}",
+                function, argument, argument, intro
+            );
+            if !arguments.is_empty() {
+                sentence += " and setting aside the remaining arguments for the result";
+            }
+            sentence
+        }
+        Term::App { func, .. } => format!("Name the applied function {}", func),
+        Term::Force(term) => format!("Force {}", term),
+        Term::Lam { .. } | Term::Freeze(_) | Term::Hole => format!("Reduce {}", term),
+    }
+}
+
+/// Whether [`step`] takes a step in `term` as a binding or an argument rather
+/// than interpreting it.
+fn steps(env: &Env, term: &Term) -> bool {
+    !matches!(term, Term::Var(_)) && matches!(step(env, term), Ok(Some(_)))
+}
+
+/// Trims the closures of lambdas to the bindings their bodies refer to,
+/// remembering how it trimmed each lambda in the bodies it trimmed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Trimmer {
+    /// How each lambda that isn't under another lambda in a trimmed body is
+    /// trimmed, keyed by the address of its body, or `None` until it is first
+    /// trimmed.
+    trims: HashMap<usize, Option<Trim>>,
+    /// Every trimmed body, kept alive so that the addresses in `trims` aren't
+    /// reused.
+    bodies: Vec<Rc<Term>>,
+}
+
+/// The sorted indices of the free variables of a lambda, and its body
+/// reindexed to refer to just those bindings.
+#[derive(Clone, Debug)]
+struct Trim {
+    indices: Rc<[usize]>,
+    body: Rc<Term>,
+}
+
+/// Restricts `env` to the bindings that are free in `λ<intro> <body>` like
+/// [`Trimmer::trim`], but without remembering how `body` is trimmed, for when
+/// the lambdas in the trimmed body aren't interpreted in place.
+pub(crate) fn trim(env: &Env, intro: &NameIntro, body: &Term) -> (Env, Rc<Term>) {
+    Trim::new(intro, body).restrict(env, body)
+}
+
+/// The number of trimmed bodies that a [`Trimmer`] keeps before it forgets
+/// them all, so that a long interpretation doesn't keep every body alive.
+const MAX_TRIMMED_BODIES: usize = 4096;
+
+impl Trimmer {
+    /// Restricts `env` to the bindings that are free in `λ<intro> <body>`, and
+    /// reindexes `body` to refer to them in the restricted environment. If
+    /// some variable isn't bound by `env`, nothing is trimmed so that looking
+    /// it up still fails in the same way. A lambda in a body returned by an
+    /// earlier call is only trimmed the first time, and then shares its
+    /// trimmed body, so interpreting it again doesn't traverse it.
+    pub(crate) fn trim(&mut self, env: &Env, intro: &NameIntro, body: &Term) -> (Env, Rc<Term>) {
+        let key = body as *const Term as usize;
+        match self.trims.get(&key) {
+            Some(Some(trim)) => trim.restrict(env, body),
+            Some(None) => {
+                let trim = self.trim_body(intro, body);
+                // the body that `key` is in is only still alive if it wasn't
+                // forgotten
+                if let Some(slot) = self.trims.get_mut(&key) {
+                    *slot = Some(trim.clone());
+                }
+                trim.restrict(env, body)
+            }
+            None => self.trim_body(intro, body).restrict(env, body),
+        }
+    }
+
+    /// Trims `λ<intro> <body>`, and remembers the lambdas in the trimmed body
+    /// so that they are only trimmed once.
+    fn trim_body(&mut self, intro: &NameIntro, body: &Term) -> Trim {
+        let trim = Trim::new(intro, body);
+        if self.bodies.len() == MAX_TRIMMED_BODIES {
+            self.trims.clear();
+            self.bodies.clear();
+        }
+        self.remember_lambdas(&trim.body);
+        self.bodies.push(trim.body.clone());
+        trim
+    }
+
+    /// Adds the bodies of the lambdas in `term` that aren't under another
+    /// lambda to `trims`, since those are the ones interpreted along with it.
+    fn remember_lambdas(&mut self, term: &Term) {
+        match term {
+            Term::Lam { body, .. } => {
+                self.trims
+                    .insert(body.as_ref() as *const Term as usize, None);
+            }
+            Term::Neu { arguments, .. } => {
+                arguments
+                    .iter()
+                    .for_each(|argument| self.remember_lambdas(argument));
+            }
+            Term::Def { binding, body, .. } => {
+                self.remember_lambdas(binding);
+                self.remember_lambdas(body);
+            }
+            Term::App { func, arg } => {
+                self.remember_lambdas(func);
+                self.remember_lambdas(arg);
+            }
+            Term::Force(term) => self.remember_lambdas(term),
+            // a frozen term is copied into its value before it is interpreted
+            Term::Var(_) | Term::Freeze(_) | Term::Hole => {}
+        }
+    }
+}
+
+impl Trim {
+    fn new(intro: &NameIntro, body: &Term) -> Trim {
+        let lam = Term::lam(intro.clone(), body.clone());
+        let mut indices: Vec<usize> = free_vars(&lam).iter().map(|name| name.index()).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        let Term::Lam { body, .. } = reindex(&lam, &|index| {
+            indices.binary_search(&index).unwrap_or(index)
+        }) else {
+            unreachable!()
+        };
+        Trim {
+            indices: indices.into(),
+            body: Rc::new(*body),
+        }
+    }
+
+    /// The bindings of `env` at `indices`, and the trimmed body, or `env` and
+    /// the untrimmed `body` if some index isn't bound by `env`. The bindings
+    /// after the last one that is left out are shared rather than copied.
+    fn restrict(&self, env: &Env, body: &Term) -> (Env, Rc<Term>) {
+        let len = env.len();
+        if self.indices.last().is_some_and(|index| *index >= len) {
+            return (env.clone(), Rc::new(body.clone()));
+        }
+        let shared = self
+            .indices
+            .iter()
+            .rev()
+            .zip((0..len).rev())
+            .take_while(|(kept, index)| **kept == *index)
+            .count();
+        let closure = env
+            .iter()
+            .take(len - shared)
+            .enumerate()
+            .filter(|(index, _)| self.indices.binary_search(index).is_ok())
+            .map(|(_, binding)| binding.clone())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .fold(env.drop_bindings(len - shared), |closure, (intro, val)| {
+                closure.extend(intro, val)
+            });
+        (closure, self.body.clone())
+    }
+}
+
+/// The value of each term in each environment, keyed by [`Env::id`]. Each
+/// environment is kept alive so that its identifier isn't reused.
+type Memo = HashMap<usize, (Env, HashMap<Term, Val>)>;
+
+/// The state of a single run of the interpreter.
+#[derive(Default)]
+struct Interpreter {
+    /// The number of beta-reductions left, if limited.
+    fuel: Option<usize>,
+    /// Whether arguments and `def` bindings are bound as thunks rather than
+    /// interpreted eagerly.
+    normal_order: bool,
+    /// The number of bindings a lambda may capture without a warning, if
+    /// limited.
+    max_capture: Option<usize>,
+    warnings: Vec<LintWarning>,
+    /// How much `pending` may grow without shrinking, if limited.
+    growth_limit: Option<usize>,
+    /// The number of arguments of enclosing applications that are yet to be
+    /// applied.
+    pending: usize,
+    /// The value of `pending` at the last beta-reduction, and when it last
+    /// shrank.
+    last_pending: usize,
+    growth_start: usize,
+    /// The beta-reductions so far, if being traced.
+    trace: Option<Vec<TraceEvent>>,
+    /// The premises derived so far for each interpretation in progress, if
+    /// derivations are being recorded.
+    derivations: Option<Vec<Vec<Derivation>>>,
+    /// How deep interpretations may be nested, if limited.
+    depth_limit: Option<usize>,
+    /// The number of interpretations in progress.
+    depth: usize,
+    /// The value of each term in each environment interpreted so far, if
+    /// memoizing.
+    memo: Option<Memo>,
+    /// Whether variables that aren't bound evaluate to neutrals.
+    open: bool,
+    /// The number of beta-reductions so far.
+    betas: usize,
+    trimmer: Trimmer,
+}
+
+impl Interpreter {
+    /// Accounts for a single beta-reduction.
+    fn beta(&mut self) -> Result<(), InterpretError> {
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                return Err(InterpretError::OutOfFuel);
+            }
+            *fuel -= 1
+        }
+        if let Some(growth_limit) = self.growth_limit {
+            if self.pending < self.last_pending {
+                self.growth_start = self.pending
+            }
+            self.last_pending = self.pending;
+            let observed_growth = self.pending - self.growth_start;
+            if observed_growth > growth_limit {
+                return Err(InterpretError::LikelyDivergent { observed_growth });
+            }
+        }
+        self.betas += 1;
+        Ok(())
+    }
+
+    /// Warns if a lambda with `binder` would capture more than `max_capture`
+    /// bindings of `env`.
+    fn capture(&mut self, binder: &NameIntro, env: &Env) {
+        if self
+            .max_capture
+            .is_some_and(|max_capture| env.len() > max_capture)
+        {
+            self.warnings.push(LintWarning::LargeCapture {
+                binder: binder.clone(),
+                size: env.len(),
+            })
+        }
+    }
+
+    /// Looks up `name` in `env`, or makes a neutral for it if it isn't bound and
+    /// the interpreter is open.
+    fn lookup(&self, env: &Env, name: &NameRef) -> Result<Box<Val>, InterpretError> {
+        match env.lookup(name) {
+            Err(InterpretError::UnboundVariable { label, index }) if self.open => Ok(Box::new(
+                Val::neutral(NameRef::new(&label, index - env.len()), vec![]),
+            )),
+            result => result,
+        }
+    }
+
+    /// Interprets `val` if it is a thunk.
+    fn force(&mut self, val: Val) -> Result<Val, InterpretError> {
+        match val {
+            Val::Thunk { term, closure } => self.interpret(&closure, &term),
+            val => Ok(val),
+        }
+    }
+
+    /// Interprets `val` if it is a thunk or a frozen term, since it is about to
+    /// be applied.
+    fn thaw(&mut self, val: Val) -> Result<Val, InterpretError> {
+        match self.force(val)? {
+            Val::Frozen { term, closure } => {
+                let val = self.interpret(&closure, &term)?;
+                self.thaw(val)
+            }
+            val => Ok(val),
+        }
+    }
+
+    /// Binds `term` in `env` as a thunk in normal order, unless it is forced,
+    /// or else interprets it.
+    fn delay(&mut self, env: &Env, term: &Term) -> Result<Val, InterpretError> {
+        if self.normal_order && !matches!(term, Term::Force(_)) {
+            Ok(Val::thunk(env.clone(), term.clone()))
+        } else {
+            self.interpret(env, term)
+        }
+    }
+
+    fn interpret(&mut self, env: &Env, term: &Term) -> Result<Val, InterpretError> {
+        if let Some(limit) = self.depth_limit {
+            if self.depth >= limit {
+                return Err(InterpretError::StackDepthExceeded { limit });
+            }
+        }
+        if let Some(val) = self
+            .memo
+            .as_ref()
+            .and_then(|memo| memo.get(&env.id())?.1.get(term))
+        {
+            return Ok(val.clone());
+        }
+        self.depth += 1;
+        let result = self.interpret_recorded(env, term);
+        self.depth -= 1;
+        if let (Some(memo), Ok(val)) = (&mut self.memo, &result) {
+            memo.entry(env.id())
+                .or_insert_with(|| (env.clone(), HashMap::new()))
+                .1
+                .insert(term.clone(), val.clone());
+        }
+        result
+    }
+
+    /// Interprets `term`, recording its derivation if derivations are being
+    /// recorded.
+    fn interpret_recorded(&mut self, env: &Env, term: &Term) -> Result<Val, InterpretError> {
+        let Some(derivations) = &mut self.derivations else {
+            return self.interpret_term(env, term);
+        };
+        derivations.push(vec![]);
+        let result = self.interpret_term(env, term);
+        let derivations = self.derivations.as_mut().unwrap();
+        let premises = derivations.pop().unwrap();
+        let val = result?;
+        derivations.last_mut().unwrap().push(Derivation {
+            env: env.clone(),
+            term: term.clone(),
+            val: val.clone(),
+            premises,
+        });
+        Ok(val)
+    }
+
+    /// Interprets `term` without recording its derivation.
+    fn interpret_term(&mut self, env: &Env, term: &Term) -> Result<Val, InterpretError> {
+        match term {
+            Term::Lam { intro: name, body } => {
+                let (closure, body) = self.trimmer.trim(env, name, body);
+                self.capture(name, &closure);
+                Ok(Val::Lam {
+                    intro: name.clone(),
+                    body,
+                    closure: Box::new(closure),
+                })
+            }
+            Term::Var(name) => self.force(*self.lookup(env, name)?),
+            Term::Neu {
+                applicant,
+                arguments,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|arg| self.delay(env, arg))
+                    .collect::<Result<Vec<Val>, InterpretError>>()?;
+                let val = self.lookup(env, applicant)?;
+                self.apply(Some(applicant), &val, arguments)
+            }
+            Term::App { func, arg } => self.interpret_app(env, func, arg),
+            Term::Def {
+                intro: name,
+                binding,
+                body,
+            } => {
+                let binding = self.delay(env, binding)?;
+                let env = env.extend(name.clone(), Box::new(binding));
+                self.interpret(&env, body)
+            }
+            Term::Freeze(term) => Ok(Val::frozen(env.clone(), term.as_ref().clone())),
+            Term::Force(term) => self.interpret(env, term),
+            Term::Hole => Err(InterpretError::ReachedHole),
+        }
+    }
+
+    /// Interprets `func` and applies it to `arg`.
+    fn interpret_app(&mut self, env: &Env, func: &Term, arg: &Term) -> Result<Val, InterpretError> {
+        let func = self.interpret(env, func)?;
+        let arg = self.delay(env, arg)?;
+        self.apply(None, &func, vec![arg])
+    }
+
+    /// Applies `applicant`, the value of `name` if it is named, to `arguments`
+    /// in order.
+    fn apply(
+        &mut self,
+        name: Option<&NameRef>,
+        applicant: &Val,
+        arguments: Vec<Val>,
+    ) -> Result<Val, InterpretError> {
+        let mut applicant = applicant.clone();
+        let mut remaining = arguments.len();
+        for argument in arguments {
+            remaining -= 1;
+            match self.thaw(applicant)? {
+                Val::Lam {
+                    closure,
+                    intro,
+                    body,
+                } => {
+                    self.pending += remaining;
+                    let result = self.beta().and_then(|()| {
+                        if let Some(trace) = &mut self.trace {
+                            trace.push(TraceEvent {
+                                applicant: name.cloned(),
+                                argument: argument.clone(),
+                                env: closure.extend(intro.clone(), Box::new(argument.clone())),
+                            })
+                        }
+                        let closure = closure.extend(intro.clone(), Box::new(argument));
+                        self.interpret(&closure, &body.clone())
+                    });
+                    self.pending -= remaining;
+                    applicant = result?
+                }
+                Val::Neutral {
+                    applicant: head,
+                    mut arguments,
+                } => {
+                    arguments.push(Box::new(argument));
+                    applicant = Val::Neutral {
+                        applicant: head,
+                        arguments,
+                    }
+                }
+                Val::Thunk { .. } | Val::Frozen { .. } => {
+                    unreachable!("thawed values are not thunks or frozen")
+                }
+            }
+        }
+        Ok(applicant)
+    }
+
+    /// Reads back `val` under `depth` binders introduced by readback.
+    fn readback_at(&mut self, depth: usize, val: &Val) -> Result<Term, InterpretError> {
+        match val {
+            Val::Lam {
+                intro,
+                body,
+                closure,
+            } => {
+                let variable = Val::neutral(NameRef::new(&intro.label, depth), vec![]);
+                let closure = closure.extend(intro.clone(), Box::new(variable));
+                let body = self.interpret(&closure, body)?;
+                Ok(Term::lam(
+                    intro.clone(),
+                    self.readback_at(depth + 1, &body)?,
+                ))
+            }
+            Val::Neutral {
+                applicant,
+                arguments,
+            } => {
+                let index = depth.checked_sub(applicant.index() + 1).ok_or(
+                    InterpretError::UnboundNeutral {
+                        label: applicant.label().to_string(),
+                        level: applicant.index(),
+                        depth,
+                    },
+                )?;
+                Ok(Term::neu(
+                    NameRef::new(applicant.label(), index),
+                    arguments
+                        .iter()
+                        .map(|argument| self.readback_at(depth, argument))
+                        .collect::<Result<Vec<Term>, InterpretError>>()?,
+                ))
+            }
+            Val::Thunk { .. } => {
+                let val = self.force(val.clone())?;
+                self.readback_at(depth, &val)
+            }
+            Val::Frozen { term, closure } => self.readback_frozen(depth, term, closure),
+        }
+    }
+
+    /// Reads back `val` like [`Interpreter::readback_at`], but only `cutoff`
+    /// arguments deep, with a [`Term::Hole`] in place of the rest. A frozen
+    /// term is forced rather than read back.
+    fn readback_prefix(
+        &mut self,
+        depth: usize,
+        val: &Val,
+        cutoff: usize,
+    ) -> Result<Term, InterpretError> {
+        if cutoff == 0 {
+            return Ok(Term::Hole);
+        }
+        match self.thaw(val.clone())? {
+            Val::Lam {
+                intro,
+                body,
+                closure,
+            } => {
+                let variable = Val::neutral(NameRef::new(&intro.label, depth), vec![]);
+                let closure = closure.extend(intro.clone(), Box::new(variable));
+                let body = self.interpret(&closure, &body)?;
+                Ok(Term::lam(
+                    intro,
+                    self.readback_prefix(depth + 1, &body, cutoff)?,
+                ))
+            }
+            Val::Neutral {
+                applicant,
+                arguments,
+            } => {
+                let index = depth.checked_sub(applicant.index() + 1).ok_or(
+                    InterpretError::UnboundNeutral {
+                        label: applicant.label().to_string(),
+                        level: applicant.index(),
+                        depth,
+                    },
+                )?;
+                Ok(Term::neu(
+                    NameRef::new(applicant.label(), index),
+                    arguments
+                        .iter()
+                        .map(|argument| self.readback_prefix(depth, argument, cutoff - 1))
+                        .collect::<Result<Vec<Term>, InterpretError>>()?,
+                ))
+            }
+            Val::Thunk { .. } | Val::Frozen { .. } => unreachable!("thawed values are stuck"),
+        }
+    }
+
+    /// Reads back the frozen `term` by substituting the read back values of its
+    /// free variables in `closure`, leaving the rest of it as it is.
+    fn readback_frozen(
+        &mut self,
+        depth: usize,
+        term: &Term,
+        closure: &Env,
+    ) -> Result<Term, InterpretError> {
+        let free = free_vars(term);
+        if let Some(name) = free.iter().find(|name| name.index() >= closure.len()) {
+            return Err(InterpretError::UnboundVariable {
+                label: name.label().to_string(),
+                index: name.index(),
+            });
+        }
+        let len = free.iter().map(|name| name.index() + 1).max().unwrap_or(0);
+        let mut term = term.clone();
+        // remove the binders of `closure` one at a time, innermost first, so
+        // the rest of `closure` is always just before the readback binders
+        for (index, (_, val)) in closure.iter().take(len).enumerate() {
+            if free.iter().any(|name| name.index() == index) {
+                let val = self.readback_at(depth, val)?;
+                term = subst(&term, 0, &shift(&val, 0, (len - index) as isize));
+            }
+            term = shift(&term, 0, -1);
+        }
+        Ok(Term::freeze(term))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use proptest::prelude::*;
+
+    use crate::ulc::{
+        church::church_numeral,
+        combinators::{omega, y},
+        interpretation::{
+            apply, bohm_prefix, def_eq, evaluate, evaluate_with_fuel, explain,
+            find_distinguishing_argument, head_variable, interpret, interpret_counting,
+            interpret_memoized, interpret_no_shadow, interpret_normal_order, interpret_open,
+            interpret_traced, interpret_with_capture_warnings, interpret_with_depth_limit,
+            interpret_with_derivation, interpret_with_fuel, interpret_with_growth_guard,
+            is_normal_form, joinable, normal_form_within, normalize, normalize_batch, readback,
+            readback_normal_order, step, whnf, InterpretError, Interpreter, TraceEvent, Trimmer,
+        },
+        lint::LintWarning,
+        machine::Machine,
+        render::diff,
+        syntax::{
+            alpha_eq, shift, subst, term_builder, to_builder, Env, NameIntro, NameRef, Term,
+            TermBuilder, Val,
+        },
+    };
+
+    fn assert_interpret(env: Env, term: Term, expected_val: &Val) {
+        let actual_val = interpret(&env, &term);
+        assert_eq!(
+            Machine::new(&env, &term).run(),
+            actual_val,
+            "machine disagrees"
+        );
+        // point out where the bodies of lambdas differ, since the whole values
+        // are hard to compare by eye
+        let difference = match (&actual_val, expected_val) {
+            (Ok(Val::Lam { body: actual, .. }), Val::Lam { body: expected, .. }) => {
+                diff(actual, expected)
+            }
+            _ => None,
+        };
+        assert_eq!(
+            actual_val.as_ref(),
+            Ok(expected_val),
+            "\ninput:\n  {}\nactual:\n  {}\nexpected:\n  {}\ndifference in body:\n  {}",
+            term,
+            match &actual_val {
+                Ok(v) => format!("{}", v),
+                Err(e) => format!("{}", e),
+            },
+            &expected_val,
+            difference.unwrap_or_else(|| "none".to_string()),
+        );
+    }
+
+    #[test]
+    fn test1() {
+        use term_builder::*;
+        assert_interpret(
+            Env::default(),
+            // (def f = λx λy x#1 in (f λz z#0)))
+            def(
+                "f",
+                lam("x", lam("y", var("x"))),
+                neu("f", vec![lam("z", var("z"))]),
+            )
+            .into(),
+            // λ[x = λ[]z z#0]y x#1
+            &Val::lam(
+                Env::from(vec![(
+                    NameIntro::new("x"),
+                    // λ[]z z#0
+                    Val::lam(
+                        Env::default(),
+                        NameIntro::new("z"),
+                        Term::neu(NameRef::new("z", 0), vec![]),
+                    ),
+                )]),
+                NameIntro::new("y"),
+                // x#1
+                Term::neu(NameRef::new("x", 1), vec![]),
+            ),
+        );
+    }
+
+    #[test]
+    fn test2() {
+        assert_interpret(
+            Env::default(),
+            Term::lam(NameIntro::new("x"), Term::neu(NameRef::new("x", 0), vec![])),
+            &Val::lam(
+                Env::default(),
+                NameIntro::new("x"),
+                Term::neu(NameRef::new("x", 0), vec![]),
+            ),
+        );
+        assert_interpret(
+            Env::default(),
+            // (def f = λx λy x#1 in (f λz z#0)))
+            Term::def(
+                NameIntro::new("f"),
+                // λx λy x#1
+                Term::lam(
+                    NameIntro::new("x"),
+                    Term::lam(NameIntro::new("y"), Term::neu(NameRef::new("x", 1), vec![])),
+                ),
+                // (f λz z#0)
+                Term::neu(
+                    NameRef::new("f", 0),
+                    vec![Term::lam(
+                        NameIntro::new("z"),
+                        Term::neu(NameRef::new("z", 0), vec![]),
+                    )],
+                ),
+            ),
+            // λ[x = λ[]z z#0]y x#1
+            &Val::lam(
+                Env::from(vec![(
+                    NameIntro::new("x"),
+                    // λ[]z z#0
+                    Val::lam(
+                        Env::default(),
+                        NameIntro::new("z"),
+                        Term::neu(NameRef::new("z", 0), vec![]),
+                    ),
+                )]),
+                NameIntro::new("y"),
+                // x#1
+                Term::neu(NameRef::new("x", 1), vec![]),
+            ),
+        );
+    }
+
+    #[test]
+    fn test_trim_closure() {
+        use term_builder::*;
+        // (def a = λz z in (def x = λz z in (def b = λz z in λy x)))
+        let term: Term = def(
+            "a",
+            lam("z", var("z")),
+            def(
+                "x",
+                lam("z", var("z")),
+                def("b", lam("z", var("z")), lam("y", var("x"))),
+            ),
+        )
+        .into();
+        let val = interpret(&Env::default(), &term).unwrap();
+        assert_eq!(format!("{}", val), "λ[x = λ[]z z#0]y x#1");
+
+        // a lambda in a trimmed body is only trimmed once, and then shares its
+        // trimmed body in every environment, unless some variable isn't bound
+        // there
+        let mut trimmer = Trimmer::default();
+        let env = |labels: &[&str]| -> Env {
+            labels
+                .iter()
+                .map(|label| {
+                    let id = Val::lam(
+                        Env::default(),
+                        NameIntro::new("z"),
+                        Term::var(NameRef::new("z", 0)),
+                    );
+                    (NameIntro::new(label), id)
+                })
+                .collect()
+        };
+        // λw (λy x#3)
+        let (closure, outer) = trimmer.trim(
+            &env(&["a", "x", "b"]),
+            &NameIntro::new("w"),
+            &Term::lam(NameIntro::new("y"), Term::var(NameRef::new("x", 3))),
+        );
+        assert_eq!(closure, env(&["x"]));
+        let Term::Lam { intro, body } = outer.as_ref() else {
+            panic!("expected a lambda, found {}", outer)
+        };
+        let wx = env(&["w", "x"]);
+        let (closure, trimmed) = trimmer.trim(&wx, intro, body);
+        assert_eq!(closure.id(), wx.drop_bindings(1).id());
+        assert_eq!(*trimmed, Term::var(NameRef::new("x", 1)));
+        let (closure, again) = trimmer.trim(&env(&["v", "x", "c"]), intro, body);
+        assert_eq!(closure, env(&["x"]));
+        assert!(Rc::ptr_eq(&trimmed, &again));
+        // a copy of the body isn't one of the trimmed bodies
+        let (_, copy) = trimmer.trim(&wx, intro, &body.clone());
+        assert_eq!(copy, trimmed);
+        assert!(!Rc::ptr_eq(&copy, &trimmed));
+        assert_eq!(
+            trimmer.trim(&env(&["a"]), intro, body),
+            (env(&["a"]), Rc::new(body.as_ref().clone()))
+        );
+    }
+
+    #[test]
+    fn test_readback() {
+        use term_builder::*;
+        let readback_interpret =
+            |term: TermBuilder| readback(&interpret(&Env::default(), &term.into()).unwrap());
+
+        // (def f = λx λy x#1 in (f λz z#0)) ~> λy λz z#0
+        assert_eq!(
+            readback_interpret(def(
+                "f",
+                lam("x", lam("y", var("x"))),
+                neu("f", vec![lam("z", var("z"))]),
+            )),
+            Ok(Term::lam(
+                NameIntro::new("y"),
+                Term::lam(NameIntro::new("z"), Term::var(NameRef::new("z", 0)))
+            ))
+        );
+
+        // λx λy x#1 ~> λx λy x#1
+        assert_eq!(
+            readback_interpret(lam("x", lam("y", var("x")))),
+            Ok(Term::lam(
+                NameIntro::new("x"),
+                Term::lam(NameIntro::new("y"), Term::var(NameRef::new("x", 1)))
+            ))
+        );
+
+        // (def id = λz z#0 in λx λy (id (y#0 x#1))) ~> λx λy (y#0 x#1)
+        assert_eq!(
+            readback_interpret(def(
+                "id",
+                lam("z", var("z")),
+                lam("x", lam("y", neu("id", vec![neu("y", vec![var("x")])]))),
+            )),
+            Ok(Term::lam(
+                NameIntro::new("x"),
+                Term::lam(
+                    NameIntro::new("y"),
+                    Term::neu(NameRef::new("y", 0), vec![Term::var(NameRef::new("x", 1))])
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_interpret_no_shadow() {
+        use term_builder::*;
+        assert!(
+            interpret_no_shadow(&Env::default(), &lam("x", lam("x", var("x"))).into()).is_err()
+        );
+        assert!(interpret_no_shadow(&Env::default(), &lam("x", lam("y", var("x"))).into()).is_ok());
+    }
+
+    #[test]
+    fn test_interpret_error() {
+        let env = Env::from(vec![(
+            NameIntro::new("id"),
+            Val::lam(
+                Env::default(),
+                NameIntro::new("x"),
+                Term::var(NameRef::new("x", 0)),
+            ),
+        )]);
+        assert_eq!(
+            interpret(&env, &Term::var(NameRef::new("id", 1))),
+            Err(InterpretError::UnboundVariable {
+                label: "id".to_string(),
+                index: 1
+            })
+        );
+        let err = interpret(&env, &Term::var(NameRef::new("f", 0))).unwrap_err();
+        assert_eq!(
+            err,
+            InterpretError::LabelIndexMismatch {
+                index: 0,
+                expected: "f".to_string(),
+                found: "id".to_string()
+            }
+        );
+        assert_eq!(
+            format!("{}", err),
+            "environment's binding at index `0` was expected to have the name `f` but it actually has the name `id`"
+        );
+    }
+
+    #[test]
+    fn test_interpret_with_fuel() {
+        use term_builder::*;
+        // (def w = λx (x x) in (w w))
+        let omega = def(
+            "w",
+            lam("x", neu("x", vec![var("x")])),
+            neu("w", vec![var("w")]),
+        );
+        assert_eq!(
+            interpret_with_fuel(&Env::default(), &omega.into(), 100),
+            Err(InterpretError::OutOfFuel)
+        );
+
+        // (def f = λx λy x#1 in (f λz z#0)) takes a single beta-reduction
+        let term: Term = def(
+            "f",
+            lam("x", lam("y", var("x"))),
+            neu("f", vec![lam("z", var("z"))]),
+        )
+        .into();
+        assert_eq!(
+            interpret_with_fuel(&Env::default(), &term, 1),
+            interpret(&Env::default(), &term)
+        );
+        assert_eq!(
+            interpret_with_fuel(&Env::default(), &term, 0),
+            Err(InterpretError::OutOfFuel)
+        );
+    }
+
+    #[test]
+    fn test_interpret_with_depth_limit() {
+        use term_builder::*;
+        // (⟨force⟩ (⟨force⟩ ... (⟨force⟩ λx x))), nested 50 deep
+        let term = (0..50).fold(Term::from(lam("x", var("x"))), |term, _| Term::force(term));
+        assert_eq!(
+            interpret_with_depth_limit(&Env::default(), &term, 51),
+            interpret(&Env::default(), &term)
+        );
+        assert_eq!(
+            interpret_with_depth_limit(&Env::default(), &term, 50),
+            Err(InterpretError::StackDepthExceeded { limit: 50 })
+        );
+
+        // (def w = λx (x x) in (w w)) nests deeper and deeper
+        let term: Term = def(
+            "w",
+            lam("x", neu("x", vec![var("x")])),
+            neu("w", vec![var("w")]),
+        )
+        .into();
+        assert_eq!(
+            interpret_with_depth_limit(&Env::default(), &term, 100),
+            Err(InterpretError::StackDepthExceeded { limit: 100 })
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        use term_builder::*;
+        let val = |term: TermBuilder| interpret(&Env::default(), &term.into()).unwrap();
+        let k = val(lam("x", lam("y", var("x"))));
+        let id = val(lam("z", var("z")));
+        let zero = val(lam("a", lam("b", var("b"))));
+        assert_eq!(
+            apply(&k, vec![Box::new(id.clone()), Box::new(zero.clone())]),
+            Ok(id)
+        );
+
+        // the arguments of a neutral are appended to it
+        let f = Val::neutral(NameRef::new("f", 0), vec![]);
+        assert_eq!(
+            apply(&f, vec![Box::new(zero.clone())]),
+            Ok(Val::neutral(NameRef::new("f", 0), vec![zero]))
+        );
+    }
+
+    #[test]
+    fn test_interpret_memoized() {
+        use term_builder::*;
+        // (def id = λx x in (def k = λx λy x in (k (id id) (id (id id)))))
+        let term: Term = def(
+            "id",
+            lam("x", var("x")),
+            def(
+                "k",
+                lam("x", lam("y", var("x"))),
+                neu(
+                    "k",
+                    vec![
+                        neu("id", vec![var("id")]),
+                        neu("id", vec![neu("id", vec![var("id")])]),
+                    ],
+                ),
+            ),
+        )
+        .into();
+        assert_eq!(
+            interpret_memoized(&Env::default(), &term),
+            interpret(&Env::default(), &term)
+        );
+
+        // the body of `f` is interpreted in two different environments
+        // (def id = λx x in (def k = λx λy x in (def f = λx (k x) in
+        //  (def a = (f id) in (f k)))))
+        let term: Term = def(
+            "id",
+            lam("x", var("x")),
+            def(
+                "k",
+                lam("x", lam("y", var("x"))),
+                def(
+                    "f",
+                    lam("x", neu("k", vec![var("x")])),
+                    def("a", neu("f", vec![var("id")]), neu("f", vec![var("k")])),
+                ),
+            ),
+        )
+        .into();
+        assert_eq!(
+            interpret_memoized(&Env::default(), &term),
+            interpret(&Env::default(), &term)
+        );
+    }
+
+    #[test]
+    fn test_def_eq() {
+        use term_builder::*;
+        // (def id = λx x in (id y)) and y, where `y` is free
+        let y = Term::var(NameRef::new("y", 0));
+        let term = Term::def(
+            NameIntro::new("id"),
+            Term::from(lam("x", var("x"))),
+            Term::neu(NameRef::new("id", 0), vec![Term::var(NameRef::new("y", 1))]),
+        );
+        assert_eq!(def_eq(&term, &y), Ok(true));
+        assert_eq!(def_eq(&term, &Term::var(NameRef::new("z", 1))), Ok(false));
+
+        // (def k = λx λy x in (k λa a)) and λb λc c
+        let term: Term = def(
+            "k",
+            lam("x", lam("y", var("x"))),
+            neu("k", vec![lam("a", var("a"))]),
+        )
+        .into();
+        assert_eq!(
+            def_eq(&term, &lam("b", lam("c", var("c"))).into()),
+            Ok(true)
+        );
+        assert_eq!(
+            def_eq(&term, &lam("b", lam("c", var("b"))).into()),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        use term_builder::*;
+        let id = |label| lam(label, var(label));
+        // (λx λy x) a b, where a = λa a and b = λb b
+        let term = Term::from(app(app(lams(&["x", "y"], var("x")), id("a")), id("b")));
+        assert_eq!(evaluate(&term), Ok(id("a").into()));
+        assert_eq!(evaluate_with_fuel(&term, 2), Ok(id("a").into()));
+        assert_eq!(evaluate_with_fuel(&term, 1), Err(InterpretError::OutOfFuel));
+
+        // the body of the result is normalized
+        // (λx λy (x y)) (λa a)
+        let term = Term::from(app(lams(&["x", "y"], neu("x", vec![var("y")])), id("a")));
+        assert_eq!(evaluate(&term), Ok(id("y").into()));
+
+        // ω ω has no normal form
+        let term = Term::app(omega(), omega());
+        assert_eq!(
+            evaluate_with_fuel(&term, 100),
+            Err(InterpretError::OutOfFuel)
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        use term_builder::*;
+        // (def id = λx x in λf λy (f (id y) (id λz (id z))))
+        let term: Term = def(
+            "id",
+            lam("x", var("x")),
+            lam(
+                "f",
+                lam(
+                    "y",
+                    neu(
+                        "f",
+                        vec![
+                            neu("id", vec![var("y")]),
+                            neu("id", vec![lam("z", neu("id", vec![var("z")]))]),
+                        ],
+                    ),
+                ),
+            ),
+        )
+        .into();
+        let expected: Term =
+            lam("f", lam("y", neu("f", vec![var("y"), lam("z", var("z"))]))).into();
+        assert_eq!(normalize(&Env::default(), &term), Ok(expected));
+
+        // a neutral in the environment is read back as a reference to it
+        let env = Env::from(vec![
+            (
+                NameIntro::new("g"),
+                Val::neutral(NameRef::new("g", 1), vec![]),
+            ),
+            (
+                NameIntro::new("id"),
+                interpret(&Env::default(), &lam("x", var("x")).into()).unwrap(),
+            ),
+        ]);
+        let g = NameRef::new("g", 0);
+        let term = Term::neu(
+            NameRef::new("id", 1),
+            vec![Term::neu(g.clone(), vec![Term::var(g.clone())])],
+        );
+        assert_eq!(
+            normalize(&env, &term),
+            Ok(Term::neu(g.clone(), vec![Term::var(g)]))
+        );
+    }
+
+    #[test]
+    fn test_interpret_open() {
+        use term_builder::*;
+        // (def id = λx x in (id (f id λy (g y)))), where `f` and `g` are free
+        let id: Term = lam("x", var("x")).into();
+        let term = Term::def(
+            NameIntro::new("id"),
+            id.clone(),
+            Term::neu(
+                NameRef::new("id", 0),
+                vec![Term::neu(
+                    NameRef::new("f", 1),
+                    vec![
+                        Term::var(NameRef::new("id", 0)),
+                        Term::lam(
+                            NameIntro::new("y"),
+                            Term::neu(NameRef::new("g", 3), vec![Term::var(NameRef::new("y", 0))]),
+                        ),
+                    ],
+                )],
+            ),
+        );
+        assert_eq!(
+            interpret(&Env::default(), &term),
+            Err(InterpretError::UnboundVariable {
+                label: "f".to_string(),
+                index: 1
+            })
+        );
+        let val = interpret_open(&Env::default(), &term).unwrap();
+        let Val::Neutral {
+            applicant,
+            arguments,
+        } = val
+        else {
+            panic!("expected a neutral, got {}", val)
+        };
+        assert_eq!(applicant, NameRef::new("f", 0));
+        assert_eq!(arguments.len(), 2);
+        assert_eq!(*arguments[0], interpret(&Env::default(), &id).unwrap());
+
+        // a free variable in a lambda's body refers to the same context
+        // (def k = λy (g y) in (k k)), where `g` is free
+        let term = Term::def(
+            NameIntro::new("k"),
+            Term::lam(
+                NameIntro::new("y"),
+                Term::neu(NameRef::new("g", 1), vec![Term::var(NameRef::new("y", 0))]),
+            ),
+            Term::neu(NameRef::new("k", 0), vec![Term::var(NameRef::new("k", 0))]),
+        );
+        let val = interpret_open(&Env::default(), &term).unwrap();
+        assert!(matches!(
+            val,
+            Val::Neutral { applicant, arguments }
+                if applicant == NameRef::new("g", 0) && arguments.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_interpret_error_is_error() {
+        fn lookup_x() -> Result<Val, Box<dyn std::error::Error>> {
+            Ok(interpret(
+                &Env::default(),
+                &Term::var(NameRef::new("x", 0)),
+            )?)
         }
+        let err = lookup_x().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "environment doesn't have binding at index `0` of name `x`"
+        );
     }
-}
 
-fn apply(applicant: &Val, arguments: Vec<Box<Val>>) -> Result<Val, String> {
-    let mut applicant = applicant.clone();
-    for argument in &arguments {
-        match applicant {
-            Val::Lam {
-                closure,
-                intro: name,
-                body,
-            } => {
-                let closure = closure.extend(name.clone(), argument.clone());
-                applicant = interpret(&closure, &body.clone())?
+    #[test]
+    fn test_interpret_app() {
+        use term_builder::*;
+        let id = || Term::from(lam("x", var("x")));
+        let id_val = interpret(&Env::default(), &id()).unwrap();
+        // ((λx x) λy y)
+        let term = Term::app(id(), Term::from(lam("y", var("y"))));
+        assert_eq!(
+            interpret(&Env::default(), &term),
+            interpret(&Env::default(), &Term::from(lam("y", var("y"))))
+        );
+        let (_, trace) = interpret_traced(&Env::default(), &Term::app(id(), id()));
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].applicant, None);
+        assert_eq!(trace[0].argument, id_val);
+        assert!(!is_normal_form(&term));
+        assert!(is_normal_form(&Term::from(lam(
+            "f",
+            app(var("f"), lam("x", var("x")))
+        ))));
+        let (env, next) = step(&Env::default(), &term).unwrap().unwrap();
+        assert_eq!(env, Env::default());
+        assert_eq!(next.to_string(), "(def f = λx x#0 in (f#0 λy y#0))");
+    }
+
+    #[test]
+    fn test_interpret_counting() {
+        use term_builder::*;
+        // (def k = λx λy x in (k k (k k k)))
+        let term: Term = def(
+            "k",
+            lam("x", lam("y", var("x"))),
+            neu("k", vec![var("k"), neu("k", vec![var("k"), var("k")])]),
+        )
+        .into();
+        let (val, betas) = interpret_counting(&Env::default(), &term).unwrap();
+        assert_eq!(Ok(val), interpret(&Env::default(), &term));
+        assert_eq!(betas, 4);
+
+        // applying a neutral isn't a beta-reduction
+        let env = Env::from(vec![(
+            NameIntro::new("f"),
+            Val::neutral(NameRef::new("f", 0), vec![]),
+        )]);
+        let f = NameRef::new("f", 0);
+        let term = Term::neu(f.clone(), vec![Term::var(f)]);
+        assert_eq!(
+            interpret_counting(&env, &term).map(|(_, betas)| betas),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn test_whnf() {
+        use term_builder::*;
+        // (def w = λz (z z) in (def k = λx λy (w w) in (k (w w))))
+        let term: Term = def(
+            "w",
+            lam("z", neu("z", vec![var("z")])),
+            def(
+                "k",
+                lam("x", lam("y", neu("w", vec![var("w")]))),
+                neu("k", vec![neu("w", vec![var("w")])]),
+            ),
+        )
+        .into();
+        // neither the argument nor the body of the result are evaluated
+        match whnf(&Env::default(), &term) {
+            Ok(Val::Lam { intro, body, .. }) => {
+                assert_eq!(intro, NameIntro::new("y"));
+                assert!(matches!(*body, Term::Neu { .. }));
+            }
+            result => panic!("expected a lambda, got {:?}", result),
+        }
+
+        // a free variable's arguments are left as thunks
+        // (def w = λz (z z) in (f (w w))), where `f` is free
+        let env = Env::from(vec![(
+            NameIntro::new("f"),
+            Val::neutral(NameRef::new("f", 0), vec![]),
+        )]);
+        let w = NameRef::new("w", 0);
+        let term = Term::def(
+            NameIntro::new("w"),
+            Term::from(lam("z", neu("z", vec![var("z")]))),
+            Term::neu(
+                NameRef::new("f", 1),
+                vec![Term::neu(w.clone(), vec![Term::var(w)])],
+            ),
+        );
+        match whnf(&env, &term) {
+            Ok(Val::Neutral { arguments, .. }) => {
+                assert!(matches!(*arguments[0], Val::Thunk { .. }));
             }
+            result => panic!("expected a neutral, got {:?}", result),
         }
     }
-    Ok(applicant)
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::ulc::{
-        interpretation::interpret,
-        syntax::{term_builder, Env, NameIntro, NameRef, Term, Val},
-    };
+    #[test]
+    fn test_interpret_normal_order() {
+        use term_builder::*;
+        // (def k = λx λy y in (def w = λz (z z) in (k (w w))))
+        let term: Term = def(
+            "k",
+            lam("x", lam("y", var("y"))),
+            def(
+                "w",
+                lam("z", neu("z", vec![var("z")])),
+                neu("k", vec![neu("w", vec![var("w")])]),
+            ),
+        )
+        .into();
+        assert_eq!(
+            interpret_with_fuel(&Env::default(), &term, 100),
+            Err(InterpretError::OutOfFuel)
+        );
+        let val = interpret_normal_order(&Env::default(), &term).unwrap();
+        assert_eq!(readback_normal_order(&val), Ok(lam("y", var("y")).into()));
 
-    fn assert_interpret(env: Env, term: Term, expected_val: &Val) {
-        let actual_val = interpret(&Env::default(), &term);
+        // arguments are only interpreted under a binder once they're needed
+        // λa (def k = λx λy y in (def w = λz (z z) in (k (w w) a)))
+        let term: Term = lam(
+            "a",
+            def(
+                "k",
+                lam("x", lam("y", var("y"))),
+                def(
+                    "w",
+                    lam("z", neu("z", vec![var("z")])),
+                    neu("k", vec![neu("w", vec![var("w")]), var("a")]),
+                ),
+            ),
+        )
+        .into();
+        let val = interpret_normal_order(&Env::default(), &term).unwrap();
+        assert_eq!(readback_normal_order(&val), Ok(lam("a", var("a")).into()));
+    }
+
+    #[test]
+    fn test_interpret_with_capture_warnings() {
+        use term_builder::*;
+        // (def f0 = λx x in ... (def f7 = λx x in λy (f7 f6 ... f0)))
+        let names: Vec<String> = (0..8).map(|i| format!("f{}", i)).collect();
+        let body = lam(
+            "y",
+            neu(
+                &names[7],
+                names[..7].iter().rev().map(|name| var(name)).collect(),
+            ),
+        );
+        let term: Term = names
+            .iter()
+            .rev()
+            .fold(body, |body, name| def(name, lam("x", var("x")), body))
+            .into();
+        let (result, warnings) = interpret_with_capture_warnings(&Env::default(), &term, 4);
+        assert!(result.is_ok());
+        assert!(warnings.contains(&LintWarning::LargeCapture {
+            binder: NameIntro::new("y"),
+            size: 8
+        }));
+
+        // (def id = λx x in (id id))
+        let term: Term = def("id", lam("x", var("x")), neu("id", vec![var("id")])).into();
+        let (result, warnings) = interpret_with_capture_warnings(&Env::default(), &term, 4);
+        assert!(result.is_ok());
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn test_head_variable() {
+        // (x#2 a#1 b#0)
+        let term = Term::neu(
+            NameRef::new("x", 2),
+            vec![
+                Term::var(NameRef::new("a", 1)),
+                Term::var(NameRef::new("b", 0)),
+            ],
+        );
+        assert_eq!(head_variable(&term), Ok(Some(NameRef::new("x", 2))));
+
+        let term = Term::lam(NameIntro::new("y"), Term::var(NameRef::new("y", 0)));
+        assert_eq!(head_variable(&term), Ok(None));
+
+        // (def k = λa λb b in (k y#0 x#1))
+        let term = Term::def(
+            NameIntro::new("k"),
+            Term::lam(
+                NameIntro::new("a"),
+                Term::lam(NameIntro::new("b"), Term::var(NameRef::new("b", 0))),
+            ),
+            Term::neu(
+                NameRef::new("k", 0),
+                vec![
+                    Term::var(NameRef::new("y", 1)),
+                    Term::var(NameRef::new("x", 2)),
+                ],
+            ),
+        );
+        assert_eq!(head_variable(&term), Ok(Some(NameRef::new("x", 1))));
+
+        // (x#0 (ω ω)), whose argument has no value
+        let term = Term::neu(NameRef::new("x", 0), vec![Term::app(omega(), omega())]);
+        assert_eq!(head_variable(&term), Ok(Some(NameRef::new("x", 0))));
+
+        // (⟨frozen⟩ (x#0 y#1)) is forced
+        let term = Term::freeze(Term::neu(
+            NameRef::new("x", 0),
+            vec![Term::var(NameRef::new("y", 1))],
+        ));
+        assert_eq!(head_variable(&term), Ok(Some(NameRef::new("x", 0))));
+    }
+
+    #[test]
+    fn test_bohm_prefix() {
+        use term_builder::*;
+        // (x#1 (y#0 z#2))
+        let term = Term::neu(
+            NameRef::new("x", 1),
+            vec![Term::neu(
+                NameRef::new("y", 0),
+                vec![Term::var(NameRef::new("z", 2))],
+            )],
+        );
+        assert_eq!(bohm_prefix(&term, 0), Ok(Term::Hole));
         assert_eq!(
-            actual_val.as_ref(),
-            Ok(expected_val),
-            "\ninput:\n  {}\nactual:\n  {}\nexpected:\n  {}",
-            term,
-            match &actual_val {
-                Ok(v) => format!("{}", v),
-                Err(e) => e.clone(),
-            },
-            &expected_val,
+            bohm_prefix(&term, 1),
+            Ok(Term::neu(NameRef::new("x", 1), vec![Term::Hole]))
+        );
+        assert_eq!(
+            bohm_prefix(&term, 2),
+            Ok(Term::neu(
+                NameRef::new("x", 1),
+                vec![Term::neu(NameRef::new("y", 0), vec![Term::Hole])]
+            ))
+        );
+        assert_eq!(bohm_prefix(&term, 3), Ok(term));
+
+        // (λf λx f (f x)) reduced under the binders
+        let two = Term::from(lam(
+            "f",
+            lam(
+                "x",
+                neu("f", vec![app(lam("y", var("y")), neu("f", vec![var("x")]))]),
+            ),
+        ));
+        assert_eq!(
+            bohm_prefix(&two, 1).map(|term| term.to_string()),
+            Ok("λf λx (f#1 ?)".to_string())
+        );
+        assert_eq!(
+            bohm_prefix(&two, 2).map(|term| term.to_string()),
+            Ok("λf λx (f#1 (f#1 ?))".to_string())
+        );
+
+        // (Y f#0) has an infinite Böhm tree, but each prefix is finite
+        let term = Term::app(y(), Term::var(NameRef::new("f", 0)));
+        assert_eq!(
+            bohm_prefix(&term, 3).map(|term| term.to_string()),
+            Ok("(f#0 (f#0 (f#0 ?)))".to_string())
+        );
+
+        // (x#0 (ω ω)), whose argument has no head normal form
+        let term = Term::neu(NameRef::new("x", 0), vec![Term::app(omega(), omega())]);
+        assert_eq!(
+            bohm_prefix(&term, 1),
+            Ok(Term::neu(NameRef::new("x", 0), vec![Term::Hole]))
         );
     }
 
     #[test]
-    fn test1() {
+    fn test_interpret_with_derivation() {
         use term_builder::*;
-        assert_interpret(
+        // (def id = λx x in (id λy y))
+        let (val, derivation) = interpret_with_derivation(
+            &Env::default(),
+            &def(
+                "id",
+                lam("x", var("x")),
+                neu("id", vec![lam("y", var("y"))]),
+            )
+            .into(),
+        )
+        .unwrap();
+        assert_eq!(derivation.val, val);
+        // def: the binding, then the body
+        assert_eq!(derivation.premises.len(), 2);
+        assert!(derivation.premises[0].premises.is_empty());
+        // apply: the argument, then the body of `id`
+        assert_eq!(derivation.premises[1].premises.len(), 2);
+        assert_eq!(
+            format!("{}", derivation),
+            "[] ⊢ (def id = λx x#0 in (id#0 λy y#0)) ⇓ λ[]y y#0
+  [] ⊢ λx x#0 ⇓ λ[]x x#0
+  [id = λ[]x x#0] ⊢ (id#0 λy y#0) ⇓ λ[]y y#0
+    [id = λ[]x x#0] ⊢ λy y#0 ⇓ λ[]y y#0
+    [x = λ[]y y#0] ⊢ x#0 ⇓ λ[]y y#0
+"
+        );
+    }
+
+    #[test]
+    fn test_interpret_traced() {
+        use term_builder::*;
+        let id = Val::lam(
             Env::default(),
-            // (def f = λx λy x#1 in (f λz z#0)))
-            def(
-                "f",
-                lam("x", lam("y", var("x"))),
-                neu("f", vec![lam("z", var("z"))]),
+            NameIntro::new("y"),
+            Term::var(NameRef::new("y", 0)),
+        );
+        // (def id = λx x in (id λy y))
+        let (result, trace) = interpret_traced(
+            &Env::default(),
+            &def(
+                "id",
+                lam("x", var("x")),
+                neu("id", vec![lam("y", var("y"))]),
             )
             .into(),
-            // λ[x = λ[f = λ[]x λy x#1]z z#0]y x#1
-            &Val::lam(
-                Env::from(vec![(
-                    NameIntro::new("x"),
-                    // λ[f = λ[]x λy x#1]z z#0
-                    Val::lam(
-                        Env::from(vec![(
-                            NameIntro::new("f"),
-                            // λy x#1
-                            Val::lam(
-                                Env::default(),
-                                NameIntro::new("x"),
-                                Term::lam(
-                                    NameIntro::new("y"),
-                                    Term::neu(NameRef::new("x", 1), vec![]),
-                                ),
-                            ),
-                        )]),
-                        NameIntro::new("z"),
-                        Term::neu(NameRef::new("z", 0), vec![]),
+        );
+        assert_eq!(result, Ok(id.clone()));
+        assert_eq!(
+            trace,
+            vec![TraceEvent {
+                applicant: Some(NameRef::new("id", 0)),
+                argument: id.clone(),
+                env: Env::from(vec![(NameIntro::new("x"), id.clone())]),
+            }]
+        );
+
+        // the trace is kept when the body of `f` fails after being applied
+        let (result, trace) = interpret_traced(
+            &Env::default(),
+            &Term::def(
+                NameIntro::new("f"),
+                Term::lam(NameIntro::new("x"), Term::var(NameRef::new("y", 1))),
+                Term::neu(NameRef::new("f", 0), vec![Term::from(lam("y", var("y")))]),
+            ),
+        );
+        assert_eq!(
+            result,
+            Err(InterpretError::UnboundVariable {
+                label: "y".to_string(),
+                index: 1
+            })
+        );
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].applicant, Some(NameRef::new("f", 0)));
+    }
+
+    #[test]
+    fn test_freeze() {
+        use term_builder::*;
+        // (def id = λx x in λp λy (p (⟨frozen⟩ (id y)) (id y)))
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            lam(
+                "p",
+                lam(
+                    "y",
+                    neu(
+                        "p",
+                        vec![freeze(neu("id", vec![var("y")])), neu("id", vec![var("y")])],
                     ),
-                )]),
+                ),
+            ),
+        ));
+        // the frozen redex survives, with `id` substituted into it
+        let normal = readback(&interpret(&Env::default(), &term).unwrap()).unwrap();
+        assert_eq!(
+            format!("{}", normal),
+            "λp λy (p#1 (⟨frozen⟩ (def id = λx x#0 in (id#0 y#1))) y#0)"
+        );
+        assert!(is_normal_form(&normal));
+
+        // a frozen lambda is thawed to be applied
+        assert_eq!(
+            interpret(
+                &Env::default(),
+                &def(
+                    "f",
+                    freeze(lam("x", var("x"))),
+                    neu("f", vec![lam("y", var("y"))])
+                )
+                .into()
+            ),
+            Ok(Val::lam(
+                Env::default(),
                 NameIntro::new("y"),
-                // x#1
-                Term::neu(NameRef::new("x", 1), vec![]),
+                Term::var(NameRef::new("y", 0))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_force() {
+        use term_builder::*;
+        let omega = || {
+            def(
+                "w",
+                lam("x", neu("x", vec![var("x")])),
+                neu("w", vec![var("w")]),
+            )
+        };
+        let interpret_lazily = |term: TermBuilder| {
+            Interpreter {
+                fuel: Some(10),
+                normal_order: true,
+                ..Interpreter::default()
+            }
+            .interpret(&Env::default(), &term.into())
+        };
+        // ω is never needed, so it is only interpreted if it is forced
+        assert!(interpret_lazily(def("a", omega(), lam("y", var("y")))).is_ok());
+        assert_eq!(
+            interpret_lazily(def("a", force(omega()), lam("y", var("y")))),
+            Err(InterpretError::OutOfFuel)
+        );
+    }
+
+    #[test]
+    fn test_find_distinguishing_argument() {
+        use term_builder::*;
+        let k = Term::from(lam("x", lam("y", var("x"))));
+        let k_star = Term::from(lam("x", lam("y", var("y"))));
+        let witness = find_distinguishing_argument(&k, &k_star, 100);
+        // `k (λx0 x0)` is `λy λx0 x0` but `k_star (λx0 x0)` is `λy y`
+        assert_eq!(witness, Some(lam("x0", var("x0")).into()));
+
+        let id = Term::from(lam("x", var("x")));
+        let id_renamed = Term::from(lam("z", var("z")));
+        assert_eq!(find_distinguishing_argument(&id, &id_renamed, 100), None);
+    }
+
+    #[test]
+    fn test_normalize_batch() {
+        use term_builder::*;
+        let terms: Vec<Term> = (0..10)
+            .map(|n| {
+                let mul = lam(
+                    "m",
+                    lam("n", lam("f", neu("m", vec![neu("n", vec![var("f")])]))),
+                );
+                def(
+                    "mul",
+                    mul,
+                    neu(
+                        "mul",
+                        vec![
+                            to_builder(&church_numeral(n)),
+                            to_builder(&church_numeral(n % 4)),
+                        ],
+                    ),
+                )
+                .into()
+            })
+            .chain([def(
+                "w",
+                lam("x", neu("x", vec![var("x")])),
+                neu("w", vec![var("w")]),
+            )
+            .into()])
+            .collect();
+        let sequential: Vec<_> = terms
+            .iter()
+            .map(|term| normal_form_within(term, 100))
+            .collect();
+        assert_eq!(sequential.last(), Some(&Err(InterpretError::OutOfFuel)));
+        assert_eq!(normalize_batch(terms, 100), sequential);
+    }
+
+    #[test]
+    fn test_joinable() {
+        use term_builder::*;
+        // (def id = λx x in (id λy y)) and its reduct λy y
+        let redex = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![lam("y", var("y"))]),
+        ));
+        let reduct = Term::from(lam("z", var("z")));
+        assert_eq!(joinable(&redex, &reduct, 10), Ok(true));
+        // the argument ω is discarded before it diverges
+        let omega = def(
+            "w",
+            lam("x", neu("x", vec![var("x")])),
+            neu("w", vec![var("w")]),
+        );
+        let discard = Term::from(def(
+            "k",
+            lam("x", lam("y", var("y"))),
+            neu("k", vec![omega.clone(), lam("y", var("y"))]),
+        ));
+        assert_eq!(joinable(&discard, &reduct, 10), Ok(true));
+
+        let k = Term::from(lam("x", lam("y", var("x"))));
+        let k_star = Term::from(lam("x", lam("y", var("y"))));
+        assert_eq!(joinable(&k, &k_star, 10), Ok(false));
+
+        assert_eq!(
+            joinable(&omega.into(), &reduct, 10),
+            Err(InterpretError::OutOfFuel)
+        );
+    }
+
+    #[test]
+    fn test_is_normal_form() {
+        use term_builder::*;
+        assert!(is_normal_form(
+            &lam("f", lam("x", neu("f", vec![var("x")]))).into()
+        ));
+        // (def id = λx x in (id id))
+        assert!(!is_normal_form(
+            &def("id", lam("x", var("x")), neu("id", vec![var("id")])).into()
+        ));
+        // (def id = λx x in (def i = id in λy (i y)))
+        assert!(!is_normal_form(
+            &def(
+                "id",
+                lam("x", var("x")),
+                def("i", var("id"), lam("y", neu("i", vec![var("y")])))
+            )
+            .into()
+        ));
+        // (def k = (def a = λx x in λy a) in (k k))
+        assert!(!is_normal_form(
+            &def(
+                "k",
+                def("a", lam("x", var("x")), lam("y", var("a"))),
+                neu("k", vec![var("k")])
+            )
+            .into()
+        ));
+        // λf (def g = f in (g g))
+        assert!(is_normal_form(
+            &lam("f", def("g", var("f"), neu("g", vec![var("g")]))).into()
+        ));
+        // an application of an unbound variable is stuck
+        assert!(is_normal_form(&Term::neu(
+            NameRef::new("x", 0),
+            vec![Term::var(NameRef::new("y", 1))]
+        )));
+    }
+
+    #[test]
+    fn test_interpret_with_growth_guard() {
+        use term_builder::*;
+        // (def w = λx (x x x) in (w w))
+        let term: Term = def(
+            "w",
+            lam("x", neu("x", vec![var("x"), var("x")])),
+            neu("w", vec![var("w")]),
+        )
+        .into();
+        let mut interpreter = Interpreter {
+            fuel: Some(10_000),
+            growth_limit: Some(100),
+            ..Interpreter::default()
+        };
+        assert_eq!(
+            interpreter.interpret(&Env::default(), &term),
+            Err(InterpretError::LikelyDivergent {
+                observed_growth: 101
+            })
+        );
+        assert!(interpreter.fuel.unwrap() > 9_000);
+
+        // a terminating term with pending arguments doesn't trip the guard
+        // (def mul = λm λn λf (m (n f)) in (mul 3 4 f x))
+        let term: Term = lam(
+            "f",
+            lam(
+                "x",
+                def(
+                    "mul",
+                    lam(
+                        "m",
+                        lam("n", lam("g", neu("m", vec![neu("n", vec![var("g")])]))),
+                    ),
+                    neu(
+                        "mul",
+                        vec![
+                            to_builder(&church_numeral(3)),
+                            to_builder(&church_numeral(4)),
+                            var("f"),
+                            var("x"),
+                        ],
+                    ),
+                ),
             ),
+        )
+        .into();
+        let val = interpret_with_growth_guard(&Env::default(), &term, 10).unwrap();
+        assert_eq!(
+            readback(&val),
+            readback(&interpret(&Env::default(), &term).unwrap())
         );
     }
 
     #[test]
-    fn test2() {
-        assert_interpret(
-            Env::default(),
-            Term::lam(NameIntro::new("x"), Term::neu(NameRef::new("x", 0), vec![])),
-            &Val::lam(
-                Env::default(),
-                NameIntro::new("x"),
-                Term::neu(NameRef::new("x", 0), vec![]),
+    fn test_step() {
+        use term_builder::*;
+        // (def id = λz z in (def k = λx λy x in (k id k)))
+        let term: Term = def(
+            "id",
+            lam("z", var("z")),
+            def(
+                "k",
+                lam("x", lam("y", var("x"))),
+                neu("k", vec![var("id"), var("k")]),
             ),
+        )
+        .into();
+
+        let (env, term_k) = step(&Env::default(), &term).unwrap().unwrap();
+        let (env, term_app) = step(&env, &term_k).unwrap().unwrap();
+        assert_eq!(format!("{}", term_app), "(k#0 id#1 k#0)");
+
+        // only `id` is consumed; `k` is left in the continuation
+        let (mut env, mut term_step) = step(&env, &term_app).unwrap().unwrap();
+        assert_eq!(format!("{}", term_step), "(def r = λy x#2 in (k#1 r#0))");
+        match env.lookup(&NameRef::new("k", 0)).unwrap().as_ref() {
+            Val::Lam { body, .. } => assert_eq!(format!("{}", body), "(r#0 k#1)"),
+            val => panic!("unexpected continuation {}", val),
+        }
+
+        // stepping until no step applies yields the interpreted value
+        while let Some((next_env, next_term)) = step(&env, &term_step).unwrap() {
+            (env, term_step) = (next_env, next_term);
+        }
+        assert_eq!(format!("{}", term_step), "λz z#0");
+        assert_eq!(
+            interpret(&env, &term_step),
+            interpret(&Env::default(), &term)
         );
-        assert_interpret(
-            Env::default(),
-            // (def f = λx λy x#1 in (f λz z#0)))
-            Term::def(
+
+        // a binding is stepped rather than interpreted
+        // (def id = λz z in (def y = (id id) in y))
+        let term: Term = def(
+            "id",
+            lam("z", var("z")),
+            def("y", neu("id", vec![var("id")]), var("y")),
+        )
+        .into();
+        let (env, term_def) = step(&Env::default(), &term).unwrap().unwrap();
+        let (mut env, mut term_step) = step(&env, &term_def).unwrap().unwrap();
+        assert_eq!(format!("{}", term_step), "(k#0 z#1)");
+        match env.lookup(&NameRef::new("k", 0)).unwrap().as_ref() {
+            Val::Lam { intro, body, .. } => {
+                assert_eq!(format!("λ{} {}", intro, body), "λy y#0")
+            }
+            val => panic!("unexpected continuation {}", val),
+        }
+        while let Some((next_env, next_term)) = step(&env, &term_step).unwrap() {
+            (env, term_step) = (next_env, next_term);
+        }
+        assert_eq!(format!("{}", term_step), "λz z#0");
+    }
+
+    #[test]
+    fn test_explain() {
+        use term_builder::*;
+        // (def id = λx x in (id id))
+        let term: Term = def("id", lam("x", var("x")), neu("id", vec![var("id")])).into();
+        let transcript = explain(&term, 10);
+        assert!(transcript.contains("Start with (def id = λx x#0 in (id#0 id#0))."));
+        assert!(transcript.contains("Define id to be λx x#0, giving (id#0 id#0)."));
+        assert!(transcript.contains(
+            "Apply the function λx x#0 to the argument id#0, substituting id#0 for x, giving x#0."
+        ));
+        assert!(transcript.contains("Look up x#0, giving λx x#0."));
+        assert!(transcript.ends_with("λx x#0 is a function, so evaluation is done."));
+
+        assert!(explain(&term, 1).ends_with("Stopped after 1 steps."));
+
+        // (def id = λx x in (id (id id)))
+        let term: Term = def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![neu("id", vec![var("id")])]),
+        )
+        .into();
+        assert!(explain(&term, 10)
+            .contains("Reduce the argument (id#0 id#0) before applying λx x#0, giving (k#0 x#1)."));
+    }
+
+    /// The shape of a term, whose variable indices are made to fit in scope by
+    /// [`closed_lam`].
+    #[derive(Clone, Debug)]
+    enum Shape {
+        Lam(Box<Shape>),
+        Neu(usize, Vec<Shape>),
+        Def(Box<Shape>, Box<Shape>),
+    }
+
+    fn arb_shape() -> impl Strategy<Value = Shape> {
+        let leaf = any::<usize>().prop_map(|index| Shape::Neu(index, vec![]));
+        leaf.prop_recursive(4, 24, 3, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|body| Shape::Lam(Box::new(body))),
+                (any::<usize>(), prop::collection::vec(inner.clone(), 1..3))
+                    .prop_map(|(index, arguments)| Shape::Neu(index, arguments)),
+                (inner.clone(), inner)
+                    .prop_map(|(binding, body)| Shape::Def(Box::new(binding), Box::new(body))),
+            ]
+        })
+    }
+
+    /// Builds the closed term `λv0 <shape>`, taking each variable's index modulo
+    /// the number of binders in scope.
+    fn closed_lam(shape: &Shape) -> Term {
+        fn go(shape: &Shape, depth: usize) -> Term {
+            let intro = NameIntro::new(&format!("v{}", depth));
+            match shape {
+                Shape::Lam(body) => Term::lam(intro, go(body, depth + 1)),
+                Shape::Neu(index, arguments) => {
+                    let index = index % depth;
+                    Term::neu(
+                        NameRef::new(&format!("v{}", depth - 1 - index), index),
+                        arguments
+                            .iter()
+                            .map(|argument| go(argument, depth))
+                            .collect(),
+                    )
+                }
+                Shape::Def(binding, body) => {
+                    Term::def(intro, go(binding, depth), go(body, depth + 1))
+                }
+            }
+        }
+        Term::lam(NameIntro::new("v0"), go(shape, 1))
+    }
+
+    proptest! {
+        /// Applying `λx body` to `arg` by substitution agrees with applying it
+        /// in the interpreter, which binds `x` to `arg` in the environment. The
+        /// substitution follows the usual shift convention: `arg` is shifted up
+        /// by one to go under the binder for `x`, substituted for index `0`,
+        /// and then the result is shifted down by one to remove the binder.
+        #[test]
+        fn test_subst_agrees_with_interpret(lam_shape in arb_shape(), arg_shape in arb_shape()) {
+            let lam = closed_lam(&lam_shape);
+            let arg = closed_lam(&arg_shape);
+            let Term::Lam { intro: _, body } = &lam else { unreachable!() };
+
+            let substituted = shift(&subst(body, 0, &shift(&arg, 0, 1)), 0, -1);
+            // (def f = <lam> in (def a = <arg> in (f a)))
+            let applied = Term::def(
                 NameIntro::new("f"),
-                // λx λy x#1
-                Term::lam(
-                    NameIntro::new("x"),
-                    Term::lam(NameIntro::new("y"), Term::neu(NameRef::new("x", 1), vec![])),
+                lam.clone(),
+                Term::def(
+                    NameIntro::new("a"),
+                    shift(&arg, 0, 1),
+                    Term::neu(NameRef::new("f", 1), vec![Term::var(NameRef::new("a", 0))]),
                 ),
-                // (f λz z#0)
-                Term::neu(
-                    NameRef::new("f", 0),
-                    vec![Term::lam(
-                        NameIntro::new("z"),
-                        Term::neu(NameRef::new("z", 0), vec![]),
-                    )],
+            );
+
+            let fuel = 100;
+            match (evaluate_with_fuel(&substituted, fuel), evaluate_with_fuel(&applied, fuel + 1)) {
+                (Ok(substituted), Ok(applied)) => prop_assert!(
+                    alpha_eq(&substituted, &applied),
+                    "\nsubstituted:\n  {}\napplied:\n  {}",
+                    substituted,
+                    applied
                 ),
-            ),
-            // λ[x = λ[f = λ[]x λy x#1]z z#0]y x#1
-            &Val::lam(
-                Env::from(vec![(
-                    NameIntro::new("x"),
-                    // λ[f = λ[]x λy x#1]z z#0
-                    Val::lam(
-                        Env::from(vec![(
-                            NameIntro::new("f"),
-                            // λy x#1
-                            Val::lam(
-                                Env::default(),
-                                NameIntro::new("x"),
-                                Term::lam(
-                                    NameIntro::new("y"),
-                                    Term::neu(NameRef::new("x", 1), vec![]),
-                                ),
-                            ),
-                        )]),
-                        NameIntro::new("z"),
-                        Term::neu(NameRef::new("z", 0), vec![]),
-                    ),
-                )]),
-                NameIntro::new("y"),
-                // x#1
-                Term::neu(NameRef::new("x", 1), vec![]),
-            ),
-        );
+                (Err(InterpretError::OutOfFuel), _) | (_, Err(InterpretError::OutOfFuel)) => {}
+                (substituted, applied) => prop_assert!(
+                    false,
+                    "\nsubstituted:\n  {:?}\napplied:\n  {:?}",
+                    substituted,
+                    applied
+                ),
+            }
+        }
     }
 }
@@ -0,0 +1,159 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::syntax::{NameIntro, NameRef, Term};
+
+// ================================================================================
+/// ## LintWarning
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum LintWarning {
+    /// A lambda captured an environment with `size` bindings, more than the
+    /// configured maximum (see [`interpret_with_capture_warnings`]).
+    ///
+    /// [`interpret_with_capture_warnings`]: super::interpretation::interpret_with_capture_warnings
+    LargeCapture { binder: NameIntro, size: usize },
+    /// A function bound by a `def` to `arity` nested lambdas is applied to more
+    /// than `arity` arguments (see [`lint_arity`]).
+    OverApplied {
+        name: NameRef,
+        arity: usize,
+        arguments: usize,
+    },
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            LintWarning::LargeCapture { binder, size } => write!(
+                f,
+                "the lambda with binder `{}` captures an environment of `{}` bindings",
+                binder, size
+            ),
+            LintWarning::OverApplied {
+                name,
+                arity,
+                arguments,
+            } => write!(
+                f,
+                "`{}` takes `{}` arguments but is applied to `{}`",
+                name, arity, arguments
+            ),
+        }
+    }
+}
+
+/// Warns about every application of a `def`-bound function to more arguments
+/// than it has leading lambdas. This is only a heuristic, since the function
+/// may return another function, but it often means that arguments are passed to
+/// the wrong function.
+pub fn lint_arity(term: &Term) -> Vec<LintWarning> {
+    let mut warnings = vec![];
+    lint_arity_in(&mut vec![], term, &mut warnings);
+    warnings
+}
+
+/// The number of leading lambdas of `term`, where `ctx` holds the arity of
+/// each enclosing binder that is known to be bound to a lambda, innermost last.
+fn arity(ctx: &[Option<usize>], term: &Term) -> Option<usize> {
+    match term {
+        Term::Lam { intro: _, body } => Some(1 + arity(&[], body).unwrap_or(0)),
+        Term::Var(name) => lookup_arity(ctx, name),
+        Term::Neu { .. } | Term::Def { .. } | Term::App { .. } | Term::Hole => None,
+        // a frozen term is thawed when it is applied
+        Term::Freeze(term) | Term::Force(term) => arity(ctx, term),
+    }
+}
+
+fn lookup_arity(ctx: &[Option<usize>], name: &NameRef) -> Option<usize> {
+    ctx.len()
+        .checked_sub(name.index() + 1)
+        .and_then(|level| ctx[level])
+}
+
+fn lint_arity_in(ctx: &mut Vec<Option<usize>>, term: &Term, warnings: &mut Vec<LintWarning>) {
+    match term {
+        Term::Lam { intro: _, body } => {
+            ctx.push(None);
+            lint_arity_in(ctx, body, warnings);
+            ctx.pop();
+        }
+        Term::Var(_) => {}
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            if let Some(arity) = lookup_arity(ctx, applicant) {
+                if arguments.len() > arity {
+                    warnings.push(LintWarning::OverApplied {
+                        name: applicant.clone(),
+                        arity,
+                        arguments: arguments.len(),
+                    })
+                }
+            }
+            for argument in arguments {
+                lint_arity_in(ctx, argument, warnings)
+            }
+        }
+        Term::Def {
+            intro: _,
+            binding,
+            body,
+        } => {
+            lint_arity_in(ctx, binding, warnings);
+            ctx.push(arity(ctx, binding));
+            lint_arity_in(ctx, body, warnings);
+            ctx.pop();
+        }
+        Term::App { func, arg } => {
+            lint_arity_in(ctx, func, warnings);
+            lint_arity_in(ctx, arg, warnings)
+        }
+        Term::Freeze(term) | Term::Force(term) => lint_arity_in(ctx, term, warnings),
+        Term::Hole => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        lint::{lint_arity, LintWarning},
+        syntax::{term_builder::*, NameRef, Term},
+    };
+
+    #[test]
+    fn test_lint_arity() {
+        // λa λb (def f = λx x in (f a b))
+        let term = Term::from(lam(
+            "a",
+            lam(
+                "b",
+                def("f", lam("x", var("x")), neu("f", vec![var("a"), var("b")])),
+            ),
+        ));
+        assert_eq!(
+            lint_arity(&term),
+            vec![LintWarning::OverApplied {
+                name: NameRef::new("f", 0),
+                arity: 1,
+                arguments: 2
+            }]
+        );
+
+        // λa λb (def k = λx λy x in (def g = k in (g a b)))
+        let term = Term::from(lam(
+            "a",
+            lam(
+                "b",
+                def(
+                    "k",
+                    lam("x", lam("y", var("x"))),
+                    def("g", var("k"), neu("g", vec![var("a"), var("b")])),
+                ),
+            ),
+        ));
+        assert_eq!(lint_arity(&term), vec![]);
+    }
+}
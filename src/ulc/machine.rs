@@ -0,0 +1,368 @@
+use std::rc::Rc;
+
+use super::{
+    interpretation::{trim, InterpretError},
+    syntax::{Env, NameIntro, NameRef, Term, Val},
+};
+
+/// A CEK machine that interprets a term like [`interpret`] does, but keeps the
+/// work that remains to be done in an explicit continuation on the heap rather
+/// than on the call stack, so that it can take arbitrarily deep reductions.
+/// The closure of each lambda is still trimmed like [`interpret`] trims it,
+/// which traverses the lambda recursively, so the depth of the term itself is
+/// limited as before.
+///
+/// [`interpret`]: super::interpretation::interpret
+#[derive(Clone, Debug)]
+pub struct Machine {
+    /// The term being interpreted, or the value it was interpreted to.
+    control: Control,
+    /// The environment that `control` is interpreted in, if it is a term.
+    env: Env,
+    /// What to do with the value of `control`, innermost frame last.
+    kont: Vec<Frame>,
+}
+
+#[derive(Clone, Debug)]
+enum Control {
+    Term(Term),
+    Val(Val),
+}
+
+/// A piece of the continuation.
+#[derive(Clone, Debug)]
+enum Frame {
+    /// The value is an argument of `applicant`, after those in `evaluated` and
+    /// before those in `remaining`, which are in reverse order.
+    Arguments {
+        env: Env,
+        applicant: NameRef,
+        evaluated: Vec<Val>,
+        remaining: Vec<Term>,
+    },
+    /// The value is to be applied to `arguments`, which are in reverse order.
+    Apply { arguments: Vec<Val> },
+    /// The value is bound to `intro` in `body`.
+    Def {
+        env: Env,
+        intro: NameIntro,
+        body: Term,
+    },
+    /// The value is a function to be applied to `arg`, once it is interpreted.
+    Func { env: Env, arg: Term },
+    /// The value is an argument to apply `func` to.
+    Arg { func: Val },
+}
+
+impl Machine {
+    /// The machine that interprets `term` in `env`.
+    pub fn new(env: &Env, term: &Term) -> Machine {
+        Machine {
+            control: Control::Term(term.clone()),
+            env: env.clone(),
+            kont: vec![],
+        }
+    }
+
+    /// The value that the term was interpreted to, if the machine is done.
+    pub fn result(&self) -> Option<&Val> {
+        match &self.control {
+            Control::Val(val) if self.kont.is_empty() => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Runs the machine until it is done.
+    pub fn run(mut self) -> Result<Val, InterpretError> {
+        while self.result().is_none() {
+            self = self.step()?
+        }
+        match self.control {
+            Control::Val(val) => Ok(val),
+            Control::Term(_) => unreachable!("the machine is done"),
+        }
+    }
+
+    /// Takes a single transition. Does nothing if the machine is done.
+    pub fn step(self) -> Result<Machine, InterpretError> {
+        let Machine {
+            control,
+            mut env,
+            mut kont,
+        } = self;
+        let control = match control {
+            Control::Term(term) => step_term(&mut env, &mut kont, term)?,
+            Control::Val(val) => match kont.pop() {
+                Some(frame) => step_val(&mut env, &mut kont, val, frame)?,
+                None => Control::Val(val),
+            },
+        };
+        Ok(Machine { control, env, kont })
+    }
+}
+
+/// Interprets `term` in `env`, pushing onto `kont` what to do with its value.
+fn step_term(env: &mut Env, kont: &mut Vec<Frame>, term: Term) -> Result<Control, InterpretError> {
+    match term {
+        Term::Lam { intro, body } => {
+            let (closure, body) = trim(env, &intro, &body);
+            Ok(Control::Val(Val::Lam {
+                intro,
+                body,
+                closure: Box::new(closure),
+            }))
+        }
+        Term::Var(name) => force(env, *env.lookup(&name)?),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let mut remaining: Vec<Term> = arguments.into_iter().map(|arg| *arg).collect();
+            remaining.reverse();
+            match remaining.pop() {
+                Some(argument) => {
+                    kont.push(Frame::Arguments {
+                        env: env.clone(),
+                        applicant,
+                        evaluated: vec![],
+                        remaining,
+                    });
+                    Ok(Control::Term(argument))
+                }
+                None => Ok(Control::Val(*env.lookup(&applicant)?)),
+            }
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            kont.push(Frame::Def {
+                env: env.clone(),
+                intro,
+                body: *body,
+            });
+            Ok(Control::Term(*binding))
+        }
+        Term::App { func, arg } => {
+            kont.push(Frame::Func {
+                env: env.clone(),
+                arg: *arg,
+            });
+            Ok(Control::Term(*func))
+        }
+        Term::Freeze(term) => Ok(Control::Val(Val::frozen(env.clone(), *term))),
+        Term::Force(term) => Ok(Control::Term(*term)),
+        Term::Hole => Err(InterpretError::ReachedHole),
+    }
+}
+
+/// Continues with `frame` now that `val` has been found.
+fn step_val(
+    env: &mut Env,
+    kont: &mut Vec<Frame>,
+    val: Val,
+    frame: Frame,
+) -> Result<Control, InterpretError> {
+    match frame {
+        Frame::Arguments {
+            env: saved,
+            applicant,
+            mut evaluated,
+            mut remaining,
+        } => {
+            evaluated.push(val);
+            *env = saved;
+            match remaining.pop() {
+                Some(argument) => {
+                    kont.push(Frame::Arguments {
+                        env: env.clone(),
+                        applicant,
+                        evaluated,
+                        remaining,
+                    });
+                    Ok(Control::Term(argument))
+                }
+                None => {
+                    let applicant = *env.lookup(&applicant)?;
+                    evaluated.reverse();
+                    kont.push(Frame::Apply {
+                        arguments: evaluated,
+                    });
+                    Ok(Control::Val(applicant))
+                }
+            }
+        }
+        Frame::Apply { mut arguments } => {
+            let Some(argument) = arguments.pop() else {
+                return Ok(Control::Val(val));
+            };
+            match val {
+                Val::Lam {
+                    intro,
+                    body,
+                    closure,
+                } => {
+                    if !arguments.is_empty() {
+                        kont.push(Frame::Apply { arguments });
+                    }
+                    *env = closure.extend(intro, Box::new(argument));
+                    Ok(Control::Term(Rc::unwrap_or_clone(body)))
+                }
+                Val::Neutral {
+                    applicant,
+                    arguments: mut applied,
+                } => {
+                    applied.push(Box::new(argument));
+                    applied.extend(arguments.into_iter().rev().map(Box::new));
+                    Ok(Control::Val(Val::Neutral {
+                        applicant,
+                        arguments: applied,
+                    }))
+                }
+                // a thunk or a frozen term is interpreted before it is
+                // applied
+                Val::Thunk { term, closure } | Val::Frozen { term, closure } => {
+                    arguments.push(argument);
+                    kont.push(Frame::Apply { arguments });
+                    *env = *closure;
+                    Ok(Control::Term(*term))
+                }
+            }
+        }
+        Frame::Def {
+            env: saved,
+            intro,
+            body,
+        } => {
+            *env = saved.extend(intro, Box::new(val));
+            Ok(Control::Term(body))
+        }
+        Frame::Func { env: saved, arg } => {
+            kont.push(Frame::Arg { func: val });
+            *env = saved;
+            Ok(Control::Term(arg))
+        }
+        Frame::Arg { func } => {
+            kont.push(Frame::Apply {
+                arguments: vec![val],
+            });
+            Ok(Control::Val(func))
+        }
+    }
+}
+
+/// Continues with the term of `val` if it is a thunk.
+fn force(env: &mut Env, val: Val) -> Result<Control, InterpretError> {
+    match val {
+        Val::Thunk { term, closure } => {
+            *env = *closure;
+            Ok(Control::Term(*term))
+        }
+        val => Ok(Control::Val(val)),
+    }
+}
+
+/// Interprets the closed `term` with a [`Machine`], which gives the same result
+/// as [`interpret`] without using the call stack.
+///
+/// [`interpret`]: super::interpretation::interpret
+pub fn run_cek(term: &Term) -> Result<Val, InterpretError> {
+    Machine::new(&Env::default(), term).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        church::church_numeral,
+        combinators::{k, s},
+        interpretation::{interpret, interpret_with_depth_limit, InterpretError},
+        machine::{run_cek, Machine},
+        syntax::{term_builder::*, to_builder, Env, NameIntro, NameRef, Term, Val},
+    };
+
+    #[test]
+    fn test_run_cek() {
+        let church = |n| to_builder(&church_numeral(n));
+        let terms: Vec<Term> = vec![
+            lam("x", var("x")).into(),
+            def("id", lam("x", var("x")), neu("id", vec![var("id")])).into(),
+            // (s k k) is the identity
+            def(
+                "s",
+                to_builder(&s()),
+                def("k", to_builder(&k()), neu("s", vec![var("k"), var("k")])),
+            )
+            .into(),
+            // 2 2 succ, where succ = λn λf λx (f (n f x))
+            def(
+                "succ",
+                lams(
+                    &["n", "f", "x"],
+                    neu("f", vec![neu("n", vec![var("f"), var("x")])]),
+                ),
+                def("two", church(2), neu("two", vec![var("two"), var("succ")])),
+            )
+            .into(),
+            app(lam("x", var("x")), lam("y", var("y"))).into(),
+            def(
+                "k",
+                freeze(to_builder(&k())),
+                neu("k", vec![lam("a", var("a")), force(var("k"))]),
+            )
+            .into(),
+            // an unbound variable under a lambda is only looked up if it is
+            // applied
+            Term::lam(NameIntro::new("x"), Term::var(NameRef::new("y", 1))),
+            Term::neu(NameRef::new("f", 0), vec![Term::var(NameRef::new("y", 1))]),
+        ];
+        for term in terms {
+            assert_eq!(
+                run_cek(&term),
+                interpret(&Env::default(), &term),
+                "{}",
+                term
+            );
+        }
+
+        // variables bound to neutrals give neutrals
+        let env = Env::from(vec![(
+            NameIntro::new("f"),
+            Val::neutral(NameRef::new("f", 0), vec![]),
+        )]);
+        let term = Term::from(lam("f", neu("f", vec![lam("x", var("x"))])));
+        let Term::Lam { body, .. } = &term else {
+            unreachable!()
+        };
+        assert_eq!(Machine::new(&env, body).run(), interpret(&env, body));
+    }
+
+    #[test]
+    fn test_run_cek_deep() {
+        // (n id id) reduces to (id (id ... (id id))), which nests the
+        // interpretation of each argument in that of the previous one
+        let term = Term::def(
+            NameIntro::new("n"),
+            church_numeral(500),
+            Term::def(
+                NameIntro::new("id"),
+                lam("x", var("x")).into(),
+                Term::neu(
+                    NameRef::new("n", 1),
+                    vec![
+                        Term::var(NameRef::new("id", 0)),
+                        Term::var(NameRef::new("id", 0)),
+                    ],
+                ),
+            ),
+        );
+        assert_eq!(
+            interpret_with_depth_limit(&Env::default(), &term, 100),
+            Err(InterpretError::StackDepthExceeded { limit: 100 })
+        );
+        assert_eq!(
+            run_cek(&term),
+            interpret(&Env::default(), &lam("x", var("x")).into())
+        );
+    }
+}
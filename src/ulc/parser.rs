@@ -0,0 +1,516 @@
+use core::fmt;
+use std::fmt::{Display, Formatter};
+
+use super::syntax::{term_builder, TermBuilder};
+
+// ================================================================================
+/// ## ParseError
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParseError {
+    /// The byte offset in the input where parsing failed.
+    pub offset: usize,
+    /// The line of the input that `offset` is on, counting from `1`.
+    pub line: usize,
+    /// A description of what was expected at `offset`.
+    pub expected: String,
+}
+
+impl ParseError {
+    fn new(input: &str, offset: usize, expected: String) -> ParseError {
+        ParseError {
+            offset,
+            line: 1 + input[..offset].matches('\n').count(),
+            expected,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected {} at line {}, byte `{}`",
+            self.expected, self.line, self.offset
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// ================================================================================
+/// ## Token
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Lambda,
+    LParen,
+    RParen,
+    Equals,
+    Hash,
+    Def,
+    In,
+    Freeze,
+    Force,
+    Hole,
+    Ident(String),
+    Number(usize),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Token::Lambda => write!(f, "`λ`"),
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::Equals => write!(f, "`=`"),
+            Token::Hash => write!(f, "`#`"),
+            Token::Def => write!(f, "`def`"),
+            Token::In => write!(f, "`in`"),
+            Token::Freeze => write!(f, "`⟨frozen⟩`"),
+            Token::Force => write!(f, "`⟨force⟩`"),
+            Token::Hole => write!(f, "`?`"),
+            Token::Ident(label) => write!(f, "`{}`", label),
+            Token::Number(n) => write!(f, "`{}`", n),
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() && c != 'λ'
+}
+
+/// Splits `input` into tokens, each paired with its byte offset, skipping
+/// whitespace and comments.
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            // a comment extends to the end of the line
+            '-' if chars.next_if(|(_, c)| *c == '-').is_some() => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+                continue;
+            }
+            'λ' | '\\' => Token::Lambda,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '=' => Token::Equals,
+            '#' => Token::Hash,
+            '?' => Token::Hole,
+            '⟨' => {
+                let mut word = String::new();
+                while let Some((_, c)) = chars.next_if(|(_, c)| *c != '⟩') {
+                    word.push(c)
+                }
+                match (word.as_str(), chars.next()) {
+                    ("frozen", Some(_)) => Token::Freeze,
+                    ("force", Some(_)) => Token::Force,
+                    _ => {
+                        return Err(ParseError::new(
+                            input,
+                            offset,
+                            "`⟨frozen⟩` or `⟨force⟩`".to_string(),
+                        ))
+                    }
+                }
+            }
+            c if is_ident_char(c) => {
+                let mut word = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| is_ident_char(*c)) {
+                    word.push(c)
+                }
+                match word.as_str() {
+                    "def" => Token::Def,
+                    "in" => Token::In,
+                    _ if word.chars().all(|c| c.is_ascii_digit()) => {
+                        Token::Number(word.parse().map_err(|_| {
+                            ParseError::new(
+                                input,
+                                offset,
+                                "a number that fits in `usize`".to_string(),
+                            )
+                        })?)
+                    }
+                    _ => Token::Ident(word),
+                }
+            }
+            _ => {
+                return Err(ParseError::new(
+                    input,
+                    offset,
+                    "a name, `λ`, `\\`, `(`, `)`, `=`, `#`, `⟨frozen⟩`, `⟨force⟩`, or `--`"
+                        .to_string(),
+                ))
+            }
+        };
+        tokens.push((offset, token))
+    }
+    Ok(tokens)
+}
+
+/// Parses the concrete syntax that `Term`'s `Display` impl prints:
+///
+/// - `λx <Term>` (or `\x <Term>`)
+/// - `<Term> <Term> ... <Term>`, an application
+/// - `def x = <Term> in <Term>`
+/// - `⟨frozen⟩ <Term>` and `⟨force⟩ <Term>`
+/// - `<Name>`, where a name is a label optionally followed by `#<index>`
+///
+/// Whitespace, including newlines, separates tokens, and `--` starts a comment
+/// that extends to the end of the line.
+///
+/// Application is left-associative, so `f a b` is `((f a) b)`. A lambda, a
+/// `def`, or a marked term extends as far to the right as possible, so it can
+/// only be the last term of an application without parentheses: `f λx x y` is
+/// `(f λx (x y))`.
+/// Parentheses may be put around any term for grouping.
+///
+/// An application whose head isn't a name, like `(λx x) y`, is parsed as nested
+/// `App`s of the head. Names without an explicit index are resolved when the
+/// `TermBuilder` is converted to a `Term`.
+pub fn parse(input: &str) -> Result<TermBuilder, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser::new(input, &tokens);
+    let term = parser.term()?;
+    parser.expect_end()?;
+    Ok(term)
+}
+
+/// Parses a program made of a sequence of top-level statements `def <Name> =
+/// <Term>`, returning the name and term of each in order, so that each term
+/// may refer to the names defined before it. Each statement starts with a
+/// `def` at the very beginning of a line, and may span the lines up to the next
+/// such `def`, so a `def` within a term must not start a line unless it is
+/// indented. Comments and blank lines are skipped like in [`parse`].
+pub fn parse_program(input: &str) -> Result<Vec<(String, TermBuilder)>, ParseError> {
+    let tokens = tokenize(input)?;
+    let starts_line = |offset: usize| offset == 0 || input[..offset].ends_with('\n');
+    let statement_starts: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, (offset, token))| *token == Token::Def && starts_line(*offset))
+        .map(|(position, _)| position)
+        .collect();
+    if let Some((offset, _)) = tokens.first() {
+        if statement_starts.first() != Some(&0) {
+            return Err(ParseError::new(
+                input,
+                *offset,
+                "a `def` at the start of a line".to_string(),
+            ));
+        }
+    }
+    let mut parser = Parser::new(input, &tokens);
+    let mut defs = vec![];
+    for (i, start) in statement_starts.iter().enumerate() {
+        let end = statement_starts.get(i + 1).copied().unwrap_or(tokens.len());
+        parser.tokens = tokens[*start..end].to_vec();
+        parser.position = 0;
+        parser.end = tokens.get(end).map_or(input.len(), |(offset, _)| *offset);
+        parser.expect(Token::Def)?;
+        let name = parser.ident()?;
+        parser.expect(Token::Equals)?;
+        let term = parser.term()?;
+        parser.expect_end()?;
+        defs.push((name, term))
+    }
+    Ok(defs)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// The byte offset of the end of `tokens`, which is reported as the place
+    /// of an error past the last token.
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, tokens: &[(usize, Token)]) -> Parser<'a> {
+        Parser {
+            input,
+            tokens: tokens.to_vec(),
+            position: 0,
+            end: input.len(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(offset, _)| *offset)
+    }
+
+    fn error(&self, expected: String) -> ParseError {
+        ParseError::new(self.input, self.offset(), expected)
+    }
+
+    fn expect_end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(self.error(format!("end of input but found {}", token))),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        if self.peek() == Some(&expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("{}", expected)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Ident(label)) => {
+                let label = label.clone();
+                self.position += 1;
+                Ok(label)
+            }
+            _ => Err(self.error("a name".to_string())),
+        }
+    }
+
+    /// `<Name>` = `<Label>` | `<Label>#<Index>`
+    fn name(&mut self) -> Result<(String, Option<usize>), ParseError> {
+        let label = self.ident()?;
+        if self.peek() != Some(&Token::Hash) {
+            return Ok((label, None));
+        }
+        self.position += 1;
+        match self.peek() {
+            Some(Token::Number(index)) => {
+                let index = *index;
+                self.position += 1;
+                Ok((label, Some(index)))
+            }
+            _ => Err(self.error("an index".to_string())),
+        }
+    }
+
+    fn term(&mut self) -> Result<TermBuilder, ParseError> {
+        match self.peek() {
+            Some(Token::Lambda) => {
+                self.position += 1;
+                let name = self.ident()?;
+                let body = self.term()?;
+                Ok(term_builder::lam(&name, body))
+            }
+            Some(Token::Def) => {
+                self.position += 1;
+                let name = self.ident()?;
+                self.expect(Token::Equals)?;
+                let binding = self.term()?;
+                self.expect(Token::In)?;
+                let body = self.term()?;
+                Ok(term_builder::def(&name, binding, body))
+            }
+            Some(Token::Freeze) => {
+                self.position += 1;
+                Ok(term_builder::freeze(self.term()?))
+            }
+            Some(Token::Force) => {
+                self.position += 1;
+                Ok(term_builder::force(self.term()?))
+            }
+            _ => self.application(),
+        }
+    }
+
+    /// `<Atom> <Atom> ... <Atom>`, where the last argument may also be a
+    /// lambda, a `def`, or a marked term.
+    fn application(&mut self) -> Result<TermBuilder, ParseError> {
+        let head = self.atom()?;
+        let mut arguments = vec![];
+        loop {
+            match self.peek() {
+                Some(Token::Ident(_) | Token::Hole | Token::LParen) => arguments.push(self.atom()?),
+                Some(Token::Lambda | Token::Def | Token::Freeze | Token::Force) => {
+                    arguments.push(self.term()?);
+                    break;
+                }
+                _ => break,
+            }
+        }
+        Ok(term_builder::apps(head, arguments))
+    }
+
+    /// `<Name>` | `?` | `(<Term>)`
+    fn atom(&mut self) -> Result<TermBuilder, ParseError> {
+        match self.peek() {
+            Some(Token::Ident(_)) => Ok(neu(self.name()?, vec![])),
+            Some(Token::Hole) => {
+                self.position += 1;
+                Ok(term_builder::hole())
+            }
+            Some(Token::LParen) => {
+                self.position += 1;
+                let term = self.term()?;
+                self.expect(Token::RParen)?;
+                Ok(term)
+            }
+            _ => Err(self.error("a term".to_string())),
+        }
+    }
+}
+
+fn neu((label, index): (String, Option<usize>), arguments: Vec<TermBuilder>) -> TermBuilder {
+    match index {
+        Some(index) => term_builder::neu_with_index(&label, index, arguments),
+        None => term_builder::neu(&label, arguments),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        parser::{parse, parse_program, ParseError},
+        syntax::{term_builder::*, Term},
+    };
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("λx x"), Ok(lam("x", var("x"))));
+        assert_eq!(parse("\\x x"), Ok(lam("x", var("x"))));
+        assert_eq!(
+            parse("λf λx (f (f x))"),
+            Ok(lam("f", lam("x", neu("f", vec![neu("f", vec![var("x")])]))))
+        );
+        assert_eq!(
+            parse("  (def id = (λx x#0) in (id  id)) "),
+            Ok(def(
+                "id",
+                lam("x", var_with_index("x", 0)),
+                neu("id", vec![var("id")])
+            ))
+        );
+        assert_eq!(
+            parse("λf (f (⟨frozen⟩ f f) ⟨force⟩ f)"),
+            Ok(lam(
+                "f",
+                neu("f", vec![freeze(neu("f", vec![var("f")])), force(var("f"))])
+            ))
+        );
+        assert!(parse("⟨thawed⟩ x").is_err());
+        assert_eq!(
+            parse("λx x ? (f ?)"),
+            Ok(lam("x", neu("x", vec![hole(), neu("f", vec![hole()])])))
+        );
+    }
+
+    #[test]
+    fn test_parse_juxtaposition() {
+        assert_eq!(parse("f a b"), Ok(neu("f", vec![var("a"), var("b")])));
+        assert_eq!(parse("f a b"), parse("((f a) b)"));
+        assert_eq!(parse("λx f x"), Ok(lam("x", neu("f", vec![var("x")]))));
+        assert_eq!(
+            parse("f λx x y"),
+            Ok(neu("f", vec![lam("x", neu("x", vec![var("y")]))]))
+        );
+        assert_eq!(parse("(λx x) y"), Ok(app(lam("x", var("x")), var("y"))));
+        assert_eq!(
+            parse("λy (λx x) y#0 y"),
+            Ok(lam(
+                "y",
+                app(app(lam("x", var("x")), var_with_index("y", 0)), var("y"))
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            parse("λx (x"),
+            Err(ParseError {
+                offset: 6,
+                line: 1,
+                expected: "`)`".to_string()
+            })
+        );
+        assert_eq!(
+            parse("(def x = λy y x)"),
+            Err(ParseError {
+                offset: 16,
+                line: 1,
+                expected: "`in`".to_string()
+            })
+        );
+        assert_eq!(
+            parse("λx x )"),
+            Err(ParseError {
+                offset: 6,
+                line: 1,
+                expected: "end of input but found `)`".to_string()
+            })
+        );
+        assert!(parse("x#").is_err());
+        assert!(parse("λdef def").is_err());
+        assert_eq!(parse("λx\n  (x\n  -y)").map_err(|err| err.line), Err(3));
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let input = "\
+-- the identity
+def id = λx x
+
+-- a `def` within a term is indented
+def k = λx λy
+  def z = x in z -- the first argument
+def a = k id id
+";
+        assert_eq!(
+            parse_program(input),
+            Ok(vec![
+                ("id".to_string(), lam("x", var("x"))),
+                (
+                    "k".to_string(),
+                    lam("x", lam("y", def("z", var("x"), var("z"))))
+                ),
+                ("a".to_string(), neu("k", vec![var("id"), var("id")])),
+            ])
+        );
+        assert_eq!(parse_program(" -- nothing\n\n"), Ok(vec![]));
+        assert_eq!(parse("x -- a comment"), Ok(var("x")));
+
+        let err = parse_program("def id = λx x\n\n)").unwrap_err();
+        assert_eq!(
+            (err.line, err.expected.as_str()),
+            (3, "end of input but found `)`")
+        );
+        let err = parse_program("x\ndef id = λx x").unwrap_err();
+        assert_eq!(err.line, 1);
+        // the unfinished `def` runs into the next statement
+        let err = parse_program("def id = λx x\ndef k = def y = x\ndef z = z").unwrap_err();
+        assert_eq!((err.line, err.expected.as_str()), (3, "`in`"));
+    }
+
+    #[test]
+    fn test_parse_display_round_trip() {
+        let term = Term::from(def(
+            "f",
+            lam("x", lam("y", var("x"))),
+            neu(
+                "f",
+                vec![
+                    lam("z", var("z")),
+                    var("f"),
+                    lam("w", neu("w", vec![var("w")])),
+                    freeze(neu("f", vec![force(var("f"))])),
+                ],
+            ),
+        ));
+        let builder = parse(&format!("{}", term)).unwrap();
+        assert_eq!(Term::from(builder), term);
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{
+    interpretation::{interpret, InterpretError},
+    syntax::{free_vars, Env, NameIntro, Term, Val},
+};
+
+// ================================================================================
+/// ## Program
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Program {
+    /// A sequence of top-level definitions `def <Name> = <Term>`, where each
+    /// binding is in scope of all the definitions before it (the most recent one
+    /// at index `0`).
+    pub defs: Vec<(NameIntro, Term)>,
+}
+
+impl Program {
+    pub fn new(defs: Vec<(NameIntro, Term)>) -> Program {
+        Program { defs }
+    }
+
+    /// The indices of the definitions that the definition at `index` refers to
+    /// directly.
+    pub fn dependencies(&self, index: usize) -> BTreeSet<usize> {
+        free_vars(&self.defs[index].1)
+            .into_iter()
+            .filter(|name| name.index() < index)
+            .map(|name| index - 1 - name.index())
+            .collect()
+    }
+}
+
+/// Maps the name of each definition to the names of the definitions it refers
+/// to directly, in the order they are defined. If a name is defined more than
+/// once, the last definition wins.
+pub fn def_dependency_graph(program: &Program) -> HashMap<String, Vec<String>> {
+    (0..program.defs.len())
+        .map(|index| {
+            (
+                program.defs[index].0.label.clone(),
+                program
+                    .dependencies(index)
+                    .into_iter()
+                    .map(|dependency| program.defs[dependency].0.label.clone())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+// ================================================================================
+/// ## ProgramResult
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProgramResult {
+    /// The result of each definition, in the same order as [`Program::defs`].
+    pub vals: Vec<Result<Val, InterpretError>>,
+    pub stats: CacheStats,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct CacheStats {
+    /// The number of definitions that were interpreted.
+    pub evaluated: usize,
+    /// The number of definitions whose previous result was reused.
+    pub reused: usize,
+}
+
+/// Interprets every definition of the program in order.
+pub fn eval_program(program: &Program) -> ProgramResult {
+    eval_program_reusing(program, |_| None)
+}
+
+/// Re-interprets the definition at `changed_index` and the definitions that
+/// transitively depend on it, reusing the results in `prev` for all the others.
+///
+/// `prev` must be the result of evaluating a program that differs from
+/// `program` only in the definition at `changed_index`; if the number of
+/// definitions differs, the whole program is re-evaluated.
+///
+/// # Panics
+///
+/// Panics if `changed_index` isn't the index of a definition of `program`,
+/// like [`Program::dependencies`] does, rather than reusing every previous
+/// result as if nothing had changed.
+pub fn reeval_incremental(
+    program: &Program,
+    changed_index: usize,
+    prev: &ProgramResult,
+) -> ProgramResult {
+    assert!(
+        changed_index < program.defs.len(),
+        "changed index {} is out of range for a program of {} definitions",
+        changed_index,
+        program.defs.len()
+    );
+    if prev.vals.len() != program.defs.len() {
+        return eval_program(program);
+    }
+    let mut dirty = vec![false; program.defs.len()];
+    for index in 0..program.defs.len() {
+        dirty[index] = index == changed_index
+            || prev.vals[index].is_err()
+            || program
+                .dependencies(index)
+                .into_iter()
+                .any(|dependency| dirty[dependency]);
+    }
+    eval_program_reusing(program, |index| {
+        if dirty[index] {
+            None
+        } else {
+            prev.vals[index].as_ref().ok().cloned()
+        }
+    })
+}
+
+fn eval_program_reusing(program: &Program, cached: impl Fn(usize) -> Option<Val>) -> ProgramResult {
+    let mut env = Env::default();
+    let mut vals = Vec::with_capacity(program.defs.len());
+    let mut stats = CacheStats::default();
+    for (index, (intro, binding)) in program.defs.iter().enumerate() {
+        let val = match cached(index) {
+            Some(val) => {
+                stats.reused += 1;
+                val
+            }
+            None => {
+                stats.evaluated += 1;
+                match interpret(&env, binding) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        vals.push(Err(err));
+                        break;
+                    }
+                }
+            }
+        };
+        env = env.extend(intro.clone(), Box::new(val.clone()));
+        vals.push(Ok(val));
+    }
+    // definitions after a failed one have no environment to be interpreted in
+    for (intro, _) in program.defs.iter().skip(vals.len()) {
+        vals.push(Err(InterpretError::SkippedDefinition {
+            name: intro.clone(),
+        }));
+    }
+    ProgramResult { vals, stats }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        program::{def_dependency_graph, eval_program, reeval_incremental, CacheStats, Program},
+        syntax::{term_builder::*, NameIntro, Term, TermBuilder},
+    };
+
+    fn program(defs: Vec<(&str, TermBuilder)>) -> Program {
+        // wrap each binding in lambdas for the earlier definitions so that the
+        // builder resolves references to them, then strip the lambdas again
+        let names: Vec<&str> = defs.iter().map(|(name, _)| *name).collect();
+        Program::new(
+            defs.into_iter()
+                .enumerate()
+                .map(|(index, (name, binding))| {
+                    let mut binding = Term::from(lams(&names[..index], binding));
+                    for _ in 0..index {
+                        match binding {
+                            Term::Lam { body, .. } => binding = *body,
+                            _ => unreachable!(),
+                        }
+                    }
+                    (NameIntro::new(name), binding)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let program = program(vec![
+            ("id", lam("x", var("x"))),
+            ("k", lam("x", lam("y", var("x")))),
+            ("a", neu("k", vec![var("id")])),
+        ]);
+        assert!(program.dependencies(0).is_empty());
+        assert!(program.dependencies(1).is_empty());
+        assert_eq!(
+            program.dependencies(2).into_iter().collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn test_def_dependency_graph() {
+        let program = program(vec![
+            ("id", lam("x", var("x"))),
+            ("k", lam("x", lam("y", neu("id", vec![var("x")])))),
+            ("a", neu("k", vec![var("id")])),
+        ]);
+        let graph = def_dependency_graph(&program);
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph["id"], Vec::<String>::new());
+        assert_eq!(graph["k"], vec!["id"]);
+        assert_eq!(graph["a"], vec!["id", "k"]);
+    }
+
+    #[test]
+    fn test_reeval_incremental() {
+        let prev_program = program(vec![
+            ("id", lam("x", var("x"))),
+            ("k", lam("x", lam("y", var("x")))),
+            ("a", neu("id", vec![var("id")])),
+            ("b", neu("k", vec![var("k")])),
+        ]);
+        let prev = eval_program(&prev_program);
+        assert_eq!(
+            prev.stats,
+            CacheStats {
+                evaluated: 4,
+                reused: 0
+            }
+        );
+
+        // change `id`, which only `a` depends on
+        let program = program(vec![
+            ("id", lam("z", var("z"))),
+            ("k", lam("x", lam("y", var("x")))),
+            ("a", neu("id", vec![var("id")])),
+            ("b", neu("k", vec![var("k")])),
+        ]);
+        let result = reeval_incremental(&program, 0, &prev);
+        assert_eq!(
+            result.stats,
+            CacheStats {
+                evaluated: 2,
+                reused: 2
+            }
+        );
+        assert_eq!(result.vals, eval_program(&program).vals);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_reeval_incremental_out_of_range() {
+        let program = program(vec![("id", lam("x", var("x")))]);
+        reeval_incremental(&program, 1, &eval_program(&program));
+    }
+}
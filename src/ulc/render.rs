@@ -0,0 +1,888 @@
+use std::collections::HashSet;
+
+use super::syntax::{collect_defs, free_vars, Env, NameRef, Term, Val};
+
+/// Renders `term` like its `Display` impl, but stops after `max_chars`
+/// characters and then appends `…` along with the number of nodes that were not
+/// rendered at all. Rendering stops as soon as the budget is exhausted, so the
+/// cost is proportional to `max_chars` plus the number of omitted nodes.
+pub fn render_truncated(term: &Term, max_chars: usize) -> String {
+    let mut renderer = TruncatedRenderer {
+        out: String::new(),
+        remaining: max_chars,
+        truncated: false,
+        omitted: 0,
+    };
+    renderer.render(term);
+    if renderer.truncated {
+        renderer.out += &format!("…[{} nodes omitted]", renderer.omitted);
+    }
+    renderer.out
+}
+
+struct TruncatedRenderer {
+    out: String,
+    remaining: usize,
+    truncated: bool,
+    omitted: usize,
+}
+
+impl TruncatedRenderer {
+    fn write(&mut self, s: &str) {
+        if self.truncated {
+            return;
+        }
+        let len = s.chars().count();
+        if len <= self.remaining {
+            self.out += s;
+            self.remaining -= len;
+        } else {
+            self.out.extend(s.chars().take(self.remaining));
+            self.remaining = 0;
+            self.truncated = true;
+        }
+    }
+
+    fn render(&mut self, term: &Term) {
+        if self.truncated {
+            self.omitted += term.size();
+            return;
+        }
+        match term {
+            Term::Lam { intro, body } => {
+                self.write(&format!("λ{} ", intro));
+                self.render(body)
+            }
+            Term::Var(name) => self.write(&format!("{}", name)),
+            Term::Neu {
+                applicant,
+                arguments,
+            } => {
+                self.write(&format!("({}", applicant));
+                for (i, argument) in arguments.iter().enumerate() {
+                    if argument.is_lam() && i + 1 < arguments.len() {
+                        self.write(" (");
+                        self.render(argument);
+                        self.write(")")
+                    } else {
+                        self.write(" ");
+                        self.render(argument)
+                    }
+                }
+                self.write(")")
+            }
+            Term::Def {
+                intro,
+                binding,
+                body,
+            } => {
+                self.write(&format!("(def {} = ", intro));
+                self.render(binding);
+                self.write(" in ");
+                self.render(body);
+                self.write(")")
+            }
+            Term::App { func, arg } => {
+                if func.is_lam() {
+                    self.write("((");
+                    self.render(func);
+                    self.write(") ")
+                } else {
+                    self.write("(");
+                    self.render(func);
+                    self.write(" ")
+                }
+                self.render(arg);
+                self.write(")")
+            }
+            Term::Freeze(term) => {
+                self.write("(⟨frozen⟩ ");
+                self.render(term);
+                self.write(")")
+            }
+            Term::Force(term) => {
+                self.write("(⟨force⟩ ");
+                self.render(term);
+                self.write(")")
+            }
+            Term::Hole => self.write("?"),
+        }
+    }
+}
+
+/// Renders the leading chain of `def`s in `term` as a block with one binding
+/// per line and the `=`s aligned, followed by the body on an `in` line:
+///
+/// ```text
+/// def id = λx x#0
+/// def k  = λx λy x#1
+/// in (k#0 id#1)
+/// ```
+///
+/// A term that doesn't start with a `def` is rendered as by `Display`.
+pub fn render_def_block(term: &Term) -> String {
+    let (defs, body) = collect_defs(term);
+    if defs.is_empty() {
+        return format!("{}", body);
+    }
+    let width = defs
+        .iter()
+        .map(|(intro, _)| intro.label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (intro, binding) in defs {
+        out += &format!("def {:width$} = {}\n", intro.label, binding, width = width);
+    }
+    out += &format!("in {}", body);
+    out
+}
+
+/// Renders `term` with every application written as an explicit,
+/// left-associative `·` and every lambda as `λx. <body>`, so that
+/// `((f a) b)` is rendered as `(f · a) · b`. Names are rendered by their labels
+/// alone, and every argument other than a variable is parenthesized.
+pub fn render_explicit_app(term: &Term) -> String {
+    match term {
+        Term::Lam { intro, body } => format!("λ{}. {}", intro, render_explicit_app(body)),
+        Term::Var(name) => name.label().to_string(),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let mut out = applicant.label().to_string();
+            for (i, argument) in arguments.iter().enumerate() {
+                if i > 0 {
+                    out = format!("({})", out);
+                }
+                out = match argument.as_ref() {
+                    Term::Var(name) => format!("{} · {}", out, name.label()),
+                    argument => format!("{} · ({})", out, render_explicit_app(argument)),
+                };
+            }
+            out
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => format!(
+            "def {} = {} in {}",
+            intro,
+            render_explicit_app(binding),
+            render_explicit_app(body)
+        ),
+        Term::App { func, arg } => {
+            let func = match func.as_ref() {
+                Term::Var(name) => name.label().to_string(),
+                func => format!("({})", render_explicit_app(func)),
+            };
+            match arg.as_ref() {
+                Term::Var(name) => format!("{} · {}", func, name.label()),
+                arg => format!("{} · ({})", func, render_explicit_app(arg)),
+            }
+        }
+        Term::Freeze(term) => format!("⟨frozen⟩ ({})", render_explicit_app(term)),
+        Term::Force(term) => format!("⟨force⟩ ({})", render_explicit_app(term)),
+        Term::Hole => "?".to_string(),
+    }
+}
+
+/// Renders `term` in the syntax that [`parse`] reads, with names written as
+/// their labels alone and parentheses only where they are needed: application
+/// is left-associative and binds tighter than a lambda, a `def`, or a marker,
+/// each of which extends as far to the right as possible. So only an argument
+/// that is itself an application, or a lambda, `def`, or marked term that
+/// isn't the last argument, is parenthesized. The function of an `App` is
+/// parenthesized unless it is a name or an application that doesn't extend to
+/// the right. A binder
+/// whose label is already in scope, or is the label of a free variable, is
+/// renamed by appending a number, so that `λx λx x#1` is rendered as
+/// `λx λx1 x`.
+///
+/// [`parse`]: super::parser::parse
+pub fn to_pretty(term: &Term) -> String {
+    let free = free_vars(term)
+        .into_iter()
+        .map(|name| name.label().to_string())
+        .collect();
+    pretty(term, &mut vec![], &free)
+}
+
+/// Renders `term` where `ctx` holds the rendered names of the enclosing
+/// binders, innermost last.
+fn pretty(term: &Term, ctx: &mut Vec<String>, free: &HashSet<String>) -> String {
+    match term {
+        Term::Lam { intro, body } => {
+            let name = pretty_binder(&intro.label, ctx, free);
+            ctx.push(name.clone());
+            let body = pretty(body, ctx, free);
+            ctx.pop();
+            format!("λ{} {}", name, body)
+        }
+        Term::Var(name) => pretty_name(name, ctx),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let mut out = pretty_name(applicant, ctx);
+            for (i, argument) in arguments.iter().enumerate() {
+                let rendered = pretty(argument, ctx, free);
+                // a lambda, a `def`, or a marked term extends as far to the
+                // right as possible
+                let needs_parens = match argument.as_ref() {
+                    Term::Var(_) | Term::Hole => false,
+                    Term::Neu { .. } | Term::App { .. } => true,
+                    _ => i + 1 < arguments.len(),
+                };
+                if needs_parens {
+                    out += &format!(" ({})", rendered)
+                } else {
+                    out += &format!(" {}", rendered)
+                }
+            }
+            out
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            let binding = pretty(binding, ctx, free);
+            let name = pretty_binder(&intro.label, ctx, free);
+            ctx.push(name.clone());
+            let body = pretty(body, ctx, free);
+            ctx.pop();
+            format!("def {} = {} in {}", name, binding, body)
+        }
+        Term::App { func, arg } => {
+            let rendered_func = pretty(func, ctx, free);
+            let rendered_arg = pretty(arg, ctx, free);
+            let func_needs_parens = match func.as_ref() {
+                Term::Var(_) | Term::Hole => false,
+                Term::Neu { arguments, .. } => {
+                    arguments.last().is_some_and(|last| extends_right(last))
+                }
+                Term::App { arg, .. } => extends_right(arg),
+                _ => true,
+            };
+            let rendered_func = if func_needs_parens {
+                format!("({})", rendered_func)
+            } else {
+                rendered_func
+            };
+            match arg.as_ref() {
+                Term::Neu { .. } | Term::App { .. } => {
+                    format!("{} ({})", rendered_func, rendered_arg)
+                }
+                _ => format!("{} {}", rendered_func, rendered_arg),
+            }
+        }
+        Term::Freeze(term) => format!("⟨frozen⟩ {}", pretty(term, ctx, free)),
+        Term::Force(term) => format!("⟨force⟩ {}", pretty(term, ctx, free)),
+        Term::Hole => "?".to_string(),
+    }
+}
+
+/// Whether `term`, as the last argument of an application, is rendered
+/// without parentheses and so would take in anything that follows it.
+fn extends_right(term: &Term) -> bool {
+    !matches!(
+        term,
+        Term::Var(_) | Term::Neu { .. } | Term::App { .. } | Term::Hole
+    )
+}
+
+/// The name to render a binder with `label` as, which is distinct from the
+/// names of the enclosing binders and of the free variables.
+fn pretty_binder(label: &str, ctx: &[String], free: &HashSet<String>) -> String {
+    let is_taken = |name: &str| ctx.iter().any(|other| other == name) || free.contains(name);
+    if !is_taken(label) {
+        return label.to_string();
+    }
+    (1..)
+        .map(|n| format!("{}{}", label, n))
+        .find(|name| !is_taken(name))
+        .unwrap()
+}
+
+fn pretty_name(name: &NameRef, ctx: &[String]) -> String {
+    match ctx.len().checked_sub(name.index() + 1) {
+        Some(level) => ctx[level].clone(),
+        None => name.label().to_string(),
+    }
+}
+
+/// Renders `term` exactly like its `Display` impl, except that each lambda is
+/// written `\x body` rather than `λx body`. The parser accepts both forms, so
+/// the output round-trips.
+pub fn to_string_ascii(term: &Term) -> String {
+    let mut out = String::new();
+    write_ascii(term, &mut out);
+    out
+}
+
+fn write_ascii(term: &Term, out: &mut String) {
+    match term {
+        Term::Lam { intro, body } => {
+            *out += &format!("\\{} ", intro);
+            write_ascii(body, out);
+        }
+        Term::Var(name) => *out += &name.to_string(),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            *out += &format!("({}", applicant);
+            for (i, argument) in arguments.iter().enumerate() {
+                // a lambda extends as far to the right as possible
+                if argument.is_lam() && i + 1 < arguments.len() {
+                    *out += " (";
+                    write_ascii(argument, out);
+                    *out += ")";
+                } else {
+                    *out += " ";
+                    write_ascii(argument, out);
+                }
+            }
+            *out += ")";
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            *out += &format!("(def {} = ", intro);
+            write_ascii(binding, out);
+            *out += " in ";
+            write_ascii(body, out);
+            *out += ")";
+        }
+        Term::App { func, arg } => {
+            if func.is_lam() {
+                *out += "((";
+                write_ascii(func, out);
+                *out += ") ";
+            } else {
+                *out += "(";
+                write_ascii(func, out);
+                *out += " ";
+            }
+            write_ascii(arg, out);
+            *out += ")";
+        }
+        Term::Freeze(term) => {
+            *out += "(⟨frozen⟩ ";
+            write_ascii(term, out);
+            *out += ")";
+        }
+        Term::Force(term) => {
+            *out += "(⟨force⟩ ";
+            write_ascii(term, out);
+            *out += ")";
+        }
+        Term::Hole => *out += "?",
+    }
+}
+
+/// Renders `val` like its `Display` impl, but with only the `max_env_depth`
+/// most recent bindings of each environment, followed by `...` if there are
+/// more. The values bound in an environment are rendered with one less
+/// binding per environment, so the closures nested more than `max_env_depth`
+/// deep are all rendered as `[...]` and the output size is bounded.
+pub fn to_string_truncated(val: &Val, max_env_depth: usize) -> String {
+    let mut out = String::new();
+    write_val_truncated(val, max_env_depth, &mut out);
+    out
+}
+
+fn write_val_truncated(val: &Val, max_env_depth: usize, out: &mut String) {
+    match val {
+        Val::Lam {
+            intro,
+            body,
+            closure,
+        } => {
+            *out += "λ";
+            write_env_truncated(closure, max_env_depth, out);
+            *out += &format!("{} {}", intro, body);
+        }
+        Val::Neutral {
+            applicant,
+            arguments,
+        } => {
+            if arguments.is_empty() {
+                *out += &applicant.to_string();
+                return;
+            }
+            *out += &format!("({}", applicant);
+            for argument in arguments {
+                *out += " ";
+                write_val_truncated(argument, max_env_depth, out);
+            }
+            *out += ")";
+        }
+        Val::Thunk { term, closure } => {
+            write_env_truncated(closure, max_env_depth, out);
+            *out += &term.to_string();
+        }
+        Val::Frozen { term, closure } => {
+            *out += "⟨frozen⟩";
+            write_env_truncated(closure, max_env_depth, out);
+            *out += &term.to_string();
+        }
+    }
+}
+
+fn write_env_truncated(env: &Env, max_env_depth: usize, out: &mut String) {
+    *out += "[";
+    for (i, (name, val)) in env.iter().enumerate() {
+        if i > 0 {
+            *out += ", ";
+        }
+        if i == max_env_depth {
+            *out += "...";
+            break;
+        }
+        *out += &format!("{} = ", name);
+        write_val_truncated(val, max_env_depth.saturating_sub(1), out);
+    }
+    *out += "]";
+}
+
+/// Describes the first place where `a` and `b` differ in a pre-order traversal,
+/// or returns `None` if they are equal. The place is given as a path from the
+/// root `term` through the fields `body`, `binding`, `arg[i]`, `func`, `arg`,
+/// `frozen`, and `forced`, e.g. `term.body.arg[1]`.
+pub fn diff(a: &Term, b: &Term) -> Option<String> {
+    diff_at("term".to_string(), a, b)
+}
+
+fn diff_at(path: String, a: &Term, b: &Term) -> Option<String> {
+    let differ = |what: String| Some(format!("at `{}`: {}", path, what));
+    match (a, b) {
+        (
+            Term::Lam {
+                intro: intro_a,
+                body: body_a,
+            },
+            Term::Lam {
+                intro: intro_b,
+                body: body_b,
+            },
+        ) => {
+            if intro_a != intro_b {
+                return differ(format!(
+                    "the binders `{}` and `{}` differ",
+                    intro_a, intro_b
+                ));
+            }
+            diff_at(format!("{}.body", path), body_a, body_b)
+        }
+        (Term::Var(a), Term::Var(b)) if a != b => {
+            differ(format!("the variables `{}` and `{}` differ", a, b))
+        }
+        (Term::Var(_), Term::Var(_)) => None,
+        (
+            Term::Neu {
+                applicant: applicant_a,
+                arguments: arguments_a,
+            },
+            Term::Neu {
+                applicant: applicant_b,
+                arguments: arguments_b,
+            },
+        ) => {
+            if applicant_a != applicant_b {
+                return differ(format!(
+                    "the applicants `{}` and `{}` differ",
+                    applicant_a, applicant_b
+                ));
+            }
+            if arguments_a.len() != arguments_b.len() {
+                return differ(format!(
+                    "`{}` is applied to {} and {} arguments",
+                    applicant_a,
+                    arguments_a.len(),
+                    arguments_b.len()
+                ));
+            }
+            arguments_a
+                .iter()
+                .zip(arguments_b)
+                .enumerate()
+                .find_map(|(i, (a, b))| diff_at(format!("{}.arg[{}]", path, i), a, b))
+        }
+        (
+            Term::Def {
+                intro: intro_a,
+                binding: binding_a,
+                body: body_a,
+            },
+            Term::Def {
+                intro: intro_b,
+                binding: binding_b,
+                body: body_b,
+            },
+        ) => {
+            if intro_a != intro_b {
+                return differ(format!(
+                    "the binders `{}` and `{}` differ",
+                    intro_a, intro_b
+                ));
+            }
+            diff_at(format!("{}.binding", path), binding_a, binding_b)
+                .or_else(|| diff_at(format!("{}.body", path), body_a, body_b))
+        }
+        (
+            Term::App {
+                func: func_a,
+                arg: arg_a,
+            },
+            Term::App {
+                func: func_b,
+                arg: arg_b,
+            },
+        ) => diff_at(format!("{}.func", path), func_a, func_b)
+            .or_else(|| diff_at(format!("{}.arg", path), arg_a, arg_b)),
+        (Term::Freeze(a), Term::Freeze(b)) => diff_at(format!("{}.frozen", path), a, b),
+        (Term::Force(a), Term::Force(b)) => diff_at(format!("{}.forced", path), a, b),
+        (Term::Hole, Term::Hole) => None,
+        _ => differ(format!("`{}` and `{}` are different kinds of term", a, b)),
+    }
+}
+
+/// Renders `term` as a Graphviz DOT digraph with a node for each `λx`, each
+/// application `@` (whose first child is the applicant or function), each `def x`, each
+/// variable `x#n`, each `⟨frozen⟩` and `⟨force⟩` marker, and each hole `?`, and with edges
+/// from each node to its children in order.
+pub fn to_dot(term: &Term) -> String {
+    let mut out = "digraph {\n".to_string();
+    dot_node(term, &mut 0, &mut out);
+    out += "}\n";
+    out
+}
+
+/// Writes the nodes and edges of `term` to `out`, using `next_id` and the ids
+/// after it, and returns the id of the root node.
+fn dot_node(term: &Term, next_id: &mut usize, out: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    let mut node = |label: String| *out += &format!("  n{} [label={:?}];\n", id, label);
+    let children: Vec<usize> = match term {
+        Term::Lam { intro, body } => {
+            node(format!("λ{}", intro));
+            vec![dot_node(body, next_id, out)]
+        }
+        Term::Var(name) => {
+            node(format!("{}", name));
+            vec![]
+        }
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            node("@".to_string());
+            let applicant_id = *next_id;
+            *next_id += 1;
+            *out += &format!("  n{} [label={:?}];\n", applicant_id, applicant.to_string());
+            let mut children = vec![applicant_id];
+            for argument in arguments {
+                children.push(dot_node(argument, next_id, out))
+            }
+            children
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            node(format!("def {}", intro));
+            vec![
+                dot_node(binding, next_id, out),
+                dot_node(body, next_id, out),
+            ]
+        }
+        Term::App { func, arg } => {
+            node("@".to_string());
+            vec![dot_node(func, next_id, out), dot_node(arg, next_id, out)]
+        }
+        Term::Freeze(term) => {
+            node("⟨frozen⟩".to_string());
+            vec![dot_node(term, next_id, out)]
+        }
+        Term::Force(term) => {
+            node("⟨force⟩".to_string());
+            vec![dot_node(term, next_id, out)]
+        }
+        Term::Hole => {
+            node("?".to_string());
+            vec![]
+        }
+    };
+    for child in children {
+        *out += &format!("  n{} -> n{};\n", id, child);
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        parser::parse,
+        render::{
+            diff, render_def_block, render_explicit_app, render_truncated, to_dot, to_pretty,
+            to_string_ascii, to_string_truncated,
+        },
+        syntax::{alpha_eq, term_builder::*, Env, NameIntro, NameRef, Term, Val},
+    };
+
+    #[test]
+    fn test_render_truncated() {
+        // λf λx (f (f (f ... (f x))))
+        let term = Term::from(lam(
+            "f",
+            lam("x", (0..100).fold(var("x"), |body, _| neu("f", vec![body]))),
+        ));
+        assert_eq!(
+            render_truncated(&term, 20),
+            "λf λx (f#1 (f#1 (f#1…[98 nodes omitted]"
+        );
+        assert_eq!(render_truncated(&term, 20).chars().count(), 20 + 19);
+
+        let term = Term::from(lam("x", var("x")));
+        assert_eq!(render_truncated(&term, 100), format!("{}", term));
+        assert_eq!(render_truncated(&term, 7), format!("{}", term));
+    }
+
+    #[test]
+    fn test_render_def_block() {
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            def("k", lam("x", lam("y", var("x"))), neu("k", vec![var("id")])),
+        ));
+        assert_eq!(
+            render_def_block(&term),
+            "def id = λx x#0\ndef k  = λx λy x#1\nin (k#0 id#1)"
+        );
+        assert_eq!(render_def_block(&lam("x", var("x")).into()), "λx x#0");
+    }
+
+    #[test]
+    fn test_render_explicit_app() {
+        // ((f a) b)
+        let term = Term::neu(
+            NameRef::new("f", 2),
+            vec![
+                Term::var(NameRef::new("a", 1)),
+                Term::var(NameRef::new("b", 0)),
+            ],
+        );
+        assert_eq!(render_explicit_app(&term), "(f · a) · b");
+
+        // λx (f x)
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::neu(NameRef::new("f", 1), vec![Term::var(NameRef::new("x", 0))]),
+        );
+        assert_eq!(render_explicit_app(&term), "λx. f · x");
+
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![lam("y", var("y")), neu("id", vec![var("id")])]),
+        ));
+        assert_eq!(
+            render_explicit_app(&term),
+            "def id = λx. x in (id · (λy. y)) · (id · id)"
+        );
+    }
+
+    #[test]
+    fn test_to_pretty() {
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu(
+                "id",
+                vec![
+                    lam("y", var("y")),
+                    neu("id", vec![var("id")]),
+                    lam("z", var("z")),
+                ],
+            ),
+        ));
+        assert_eq!(to_pretty(&term), "def id = λx x in id (λy y) (id id) λz z");
+
+        // the inner `x` is renamed so that `x#1` still refers to the outer one
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::lam(
+                NameIntro::new("x"),
+                Term::neu(NameRef::new("x", 1), vec![Term::var(NameRef::new("x", 0))]),
+            ),
+        );
+        assert_eq!(to_pretty(&term), "λx λx1 x x1");
+        assert!(alpha_eq(&parse(&to_pretty(&term)).unwrap().into(), &term));
+
+        // a binder doesn't capture a free variable with the same label
+        let term = Term::lam(NameIntro::new("y"), Term::var(NameRef::new("y", 1)));
+        assert_eq!(to_pretty(&term), "λy1 y");
+    }
+
+    #[test]
+    fn test_to_pretty_parens() {
+        for (term, expected) in [
+            (
+                lam(
+                    "f",
+                    lam("g", neu("f", vec![neu("g", vec![var("f")]), var("g")])),
+                ),
+                "λf λg f (g f) g",
+            ),
+            (
+                lam("f", neu("f", vec![lam("x", var("x")), var("f")])),
+                "λf f (λx x) f",
+            ),
+            (
+                lam(
+                    "f",
+                    neu("f", vec![var("f"), lam("x", neu("x", vec![var("f")]))]),
+                ),
+                "λf f f λx x f",
+            ),
+            (
+                lam(
+                    "f",
+                    neu(
+                        "f",
+                        vec![
+                            def("k", var("f"), var("k")),
+                            freeze(neu("f", vec![var("f")])),
+                        ],
+                    ),
+                ),
+                "λf f (def k = f in k) ⟨frozen⟩ f f",
+            ),
+            (
+                def("k", lam("x", var("x")), neu("k", vec![var("k")])),
+                "def k = λx x in k k",
+            ),
+            (
+                lam(
+                    "f",
+                    app(app(lam("x", var("x")), var("f")), lam("y", var("y"))),
+                ),
+                "λf (λx x) f λy y",
+            ),
+        ] {
+            let term = Term::from(term);
+            assert_eq!(to_pretty(&term), expected);
+            assert!(alpha_eq(&parse(expected).unwrap().into(), &term));
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let term = Term::from(lam("f", neu("f", vec![var("f"), lam("x", var("x"))])));
+        assert_eq!(diff(&term, &term), None);
+        for (other, expected) in [
+            (
+                lam("f", neu("f", vec![var("f"), lam("y", var("y"))])),
+                "at `term.body.arg[1]`: the binders `x` and `y` differ",
+            ),
+            (
+                lam("f", neu("f", vec![var("f"), lam("x", var("f"))])),
+                "at `term.body.arg[1].body`: the variables `x#0` and `f#1` differ",
+            ),
+            (
+                lam("f", neu("f", vec![var("f")])),
+                "at `term.body`: `f#0` is applied to 2 and 1 arguments",
+            ),
+            (
+                lam("f", neu("f", vec![lam("x", var("x")), lam("x", var("x"))])),
+                "at `term.body.arg[0]`: `f#0` and `λx x#0` are different kinds of term",
+            ),
+        ] {
+            assert_eq!(diff(&term, &Term::from(other)), Some(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_to_string_ascii() {
+        let term = Term::from(def(
+            "k",
+            lam("x", lam("y", var("x"))),
+            neu("k", vec![lam("z", var("z")), freeze(lam("w", var("w")))]),
+        ));
+        let ascii = to_string_ascii(&term);
+        assert_eq!(ascii, term.to_string().replace('λ', "\\"));
+        assert!(!ascii.contains('λ'));
+        assert_eq!(Term::from(parse(&ascii).unwrap()), term);
+
+        let term = Term::from(lam("f", app(lam("x", var("x")), var("f"))));
+        let ascii = to_string_ascii(&term);
+        assert_eq!(ascii, "\\f ((\\x x#0) f#0)");
+        assert_eq!(Term::from(parse(&ascii).unwrap()), term);
+    }
+
+    #[test]
+    fn test_to_string_truncated() {
+        let id = Val::lam(
+            Env::default(),
+            NameIntro::new("x"),
+            Term::var(NameRef::new("x", 0)),
+        );
+        // λ[f = λ[f = λ[f = λ[]x x]y f]y f]y f
+        let nested = (0..3).fold(id.clone(), |val, _| {
+            Val::lam(
+                Env::from(vec![(NameIntro::new("f"), val)]),
+                NameIntro::new("y"),
+                Term::var(NameRef::new("f", 1)),
+            )
+        });
+        assert_eq!(to_string_truncated(&nested, 3), nested.to_string());
+        assert_eq!(
+            to_string_truncated(&nested, 2),
+            "λ[f = λ[f = λ[...]y f#1]y f#1]y f#1"
+        );
+
+        let wide = Val::lam(
+            ["a", "b", "c"]
+                .into_iter()
+                .map(|label| (NameIntro::new(label), id.clone()))
+                .collect(),
+            NameIntro::new("z"),
+            Term::var(NameRef::new("z", 0)),
+        );
+        assert_eq!(
+            to_string_truncated(&wide, 2),
+            "λ[a = λ[]x x#0, b = λ[]x x#0, ...]z z#0"
+        );
+        assert_eq!(to_string_truncated(&wide, 0), "λ[...]z z#0");
+    }
+
+    #[test]
+    fn test_to_dot() {
+        // (def id = λx x in (id id))
+        let term = Term::from(def("id", lam("x", var("x")), neu("id", vec![var("id")])));
+        assert_eq!(
+            to_dot(&term),
+            "digraph {
+  n0 [label=\"def id\"];
+  n1 [label=\"λx\"];
+  n2 [label=\"x#0\"];
+  n1 -> n2;
+  n3 [label=\"@\"];
+  n4 [label=\"id#0\"];
+  n5 [label=\"id#0\"];
+  n3 -> n4;
+  n3 -> n5;
+  n0 -> n1;
+  n0 -> n3;
+}
+"
+        );
+    }
+}
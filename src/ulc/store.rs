@@ -0,0 +1,405 @@
+use core::fmt;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fmt::{Display, Formatter},
+    hash::Hasher,
+};
+
+use super::syntax::{alpha_eq, free_vars, shift, walk, NameIntro, NameRef, Term, TermVisitor};
+
+/// The hash of a term's structure and de Bruijn indices, which is the same for
+/// all terms that are equal up to the labels of their names (see
+/// [`alpha_eq`]).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Hash(u64);
+
+impl Hash {
+    pub fn of(term: &Term) -> Hash {
+        let mut hasher = DefaultHasher::new();
+        hash_nameless(term, &mut hasher);
+        Hash(hasher.finish())
+    }
+}
+
+impl Display for Hash {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "#{:016x}", self.0)
+    }
+}
+
+fn hash_nameless(term: &Term, hasher: &mut impl Hasher) {
+    match term {
+        Term::Lam { intro: _, body } => {
+            hasher.write_u8(0);
+            hash_nameless(body, hasher)
+        }
+        Term::Var(name) => {
+            hasher.write_u8(1);
+            hasher.write_usize(name.index())
+        }
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            hasher.write_u8(2);
+            hasher.write_usize(applicant.index());
+            hasher.write_usize(arguments.len());
+            for argument in arguments {
+                hash_nameless(argument, hasher)
+            }
+        }
+        Term::Def {
+            intro: _,
+            binding,
+            body,
+        } => {
+            hasher.write_u8(3);
+            hash_nameless(binding, hasher);
+            hash_nameless(body, hasher)
+        }
+        Term::Freeze(term) => {
+            hasher.write_u8(4);
+            hash_nameless(term, hasher)
+        }
+        Term::Force(term) => {
+            hasher.write_u8(5);
+            hash_nameless(term, hasher)
+        }
+        Term::App { func, arg } => {
+            hasher.write_u8(6);
+            hash_nameless(func, hasher);
+            hash_nameless(arg, hasher)
+        }
+        Term::Hole => hasher.write_u8(7),
+    }
+}
+
+/// A content-addressed store of terms, where each term is keyed by its
+/// [`Hash`]. Terms that are equal up to labels share a key, so only the first
+/// of them to be put is stored.
+#[derive(Clone, Default, Debug)]
+pub struct TermStore {
+    terms: HashMap<Hash, Term>,
+}
+
+impl TermStore {
+    pub fn new() -> TermStore {
+        TermStore::default()
+    }
+
+    /// Stores `term` unless an equal term up to labels is already stored, and
+    /// returns its key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `term` has the same hash as a stored term that it isn't
+    /// alpha-equivalent to.
+    pub fn put(&mut self, term: Term) -> Hash {
+        let hash = Hash::of(&term);
+        let stored = self.terms.entry(hash).or_insert_with(|| term.clone());
+        assert!(
+            alpha_eq(stored, &term),
+            "hash collision between `{}` and `{}`",
+            stored,
+            term
+        );
+        hash
+    }
+
+    pub fn get(&self, hash: &Hash) -> Option<&Term> {
+        self.terms.get(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+/// Introduces a `def` for each subterm that occurs more than once in `term`,
+/// up to labels, wherever that makes the term smaller, and replaces each
+/// occurrence by a reference to it. Each `def` is introduced at the top of the
+/// scope of the innermost binder that its subterm refers to, so that all the
+/// occurrences are in its scope. The subterms that save the most are shared
+/// first, so a subterm that only occurs within a shared subterm is shared
+/// along with it.
+///
+/// The result is equal to `term` by inlining the introduced `def`s, but note
+/// that a shared subterm is interpreted as soon as its `def` is, even if it
+/// originally occurred under a lambda.
+pub fn optimize_sharing(term: &Term) -> Term {
+    match share_at_root(term) {
+        Term::Lam { intro, body } => Term::lam(intro, optimize_sharing(&body)),
+        Term::Var(name) => Term::Var(name),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            applicant,
+            arguments
+                .iter()
+                .map(|argument| optimize_sharing(argument))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(intro, optimize_sharing(&binding), optimize_sharing(&body)),
+        Term::App { func, arg } => Term::app_node(optimize_sharing(&func), optimize_sharing(&arg)),
+        Term::Freeze(term) => Term::freeze(optimize_sharing(&term)),
+        Term::Force(term) => Term::force(optimize_sharing(&term)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+/// Shares the subterms of `term` that don't refer to any binder within `term`,
+/// by introducing `def`s around it.
+fn share_at_root(term: &Term) -> Term {
+    let mut labels = Labels::default();
+    walk(term, &mut labels);
+    let mut fresh = 0;
+    let mut term = term.clone();
+    loop {
+        let mut store = TermStore::new();
+        let mut counts = vec![];
+        count_closed_subterms(&term, 0, &mut store, &mut counts);
+        let mut best: Option<(Hash, usize)> = None;
+        for (hash, count) in counts {
+            let size = store.get(&hash).unwrap().size();
+            // sharing replaces `count` copies by one `def` with `count`
+            // references to it
+            let savings = (count * size).saturating_sub(1 + size + count);
+            if savings > best.map_or(0, |(_, savings)| savings) {
+                best = Some((hash, savings))
+            }
+        }
+        let Some((hash, _)) = best else {
+            return term;
+        };
+        let label = loop {
+            let label = format!("shared{}", fresh);
+            fresh += 1;
+            if !labels.0.contains(&label) {
+                break label;
+            }
+        };
+        let shared = store.get(&hash).unwrap().clone();
+        let body = replace_closed_subterm(&shift(&term, 0, 1), &shift(&shared, 0, 1), 0, &label);
+        term = Term::def(NameIntro::new(&label), shared, body)
+    }
+}
+
+/// The subterm of `term` at `depth` with its free variables adjusted to refer
+/// to the context of `term`, if it doesn't refer to any binder within `term`.
+fn closed_subterm(subterm: &Term, depth: usize) -> Option<Term> {
+    if free_vars(subterm).iter().all(|name| name.index() >= depth) {
+        Some(shift(subterm, 0, -(depth as isize)))
+    } else {
+        None
+    }
+}
+
+/// Puts each subterm of `term` that doesn't refer to any binder within `term`
+/// into `store`, and counts its occurrences in `counts` in the order of their
+/// first occurrence. Variables are skipped, since there's nothing to gain from
+/// sharing them.
+fn count_closed_subterms(
+    term: &Term,
+    depth: usize,
+    store: &mut TermStore,
+    counts: &mut Vec<(Hash, usize)>,
+) {
+    if term.is_var() {
+        return;
+    }
+    if let Some(subterm) = closed_subterm(term, depth) {
+        let hash = store.put(subterm);
+        match counts.iter_mut().find(|(other, _)| *other == hash) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((hash, 1)),
+        }
+    }
+    match term {
+        Term::Lam { intro: _, body } => count_closed_subterms(body, depth + 1, store, counts),
+        Term::Var(_) => {}
+        Term::Neu {
+            applicant: _,
+            arguments,
+        } => {
+            for argument in arguments {
+                count_closed_subterms(argument, depth, store, counts)
+            }
+        }
+        Term::Def {
+            intro: _,
+            binding,
+            body,
+        } => {
+            count_closed_subterms(binding, depth, store, counts);
+            count_closed_subterms(body, depth + 1, store, counts)
+        }
+        Term::App { func, arg } => {
+            count_closed_subterms(func, depth, store, counts);
+            count_closed_subterms(arg, depth, store, counts)
+        }
+        Term::Freeze(term) | Term::Force(term) => count_closed_subterms(term, depth, store, counts),
+        Term::Hole => {}
+    }
+}
+
+/// Replaces each occurrence of `shared` in `term` by a reference to `label` at
+/// index `0` of the context of `term`.
+fn replace_closed_subterm(term: &Term, shared: &Term, depth: usize, label: &str) -> Term {
+    if !term.is_var()
+        && closed_subterm(term, depth).is_some_and(|subterm| alpha_eq(&subterm, shared))
+    {
+        return Term::var(NameRef::new(label, depth));
+    }
+    match term {
+        Term::Lam { intro, body } => Term::lam(
+            intro.clone(),
+            replace_closed_subterm(body, shared, depth + 1, label),
+        ),
+        Term::App { func, arg } => Term::app_node(
+            replace_closed_subterm(func, shared, depth, label),
+            replace_closed_subterm(arg, shared, depth, label),
+        ),
+        Term::Var(name) => Term::Var(name.clone()),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            applicant.clone(),
+            arguments
+                .iter()
+                .map(|argument| replace_closed_subterm(argument, shared, depth, label))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(
+            intro.clone(),
+            replace_closed_subterm(binding, shared, depth, label),
+            replace_closed_subterm(body, shared, depth + 1, label),
+        ),
+        Term::Freeze(term) => Term::freeze(replace_closed_subterm(term, shared, depth, label)),
+        Term::Force(term) => Term::force(replace_closed_subterm(term, shared, depth, label)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+/// The labels of all the binders and variables of a term.
+#[derive(Default)]
+struct Labels(HashSet<String>);
+
+impl TermVisitor for Labels {
+    fn visit_lam(&mut self, intro: &NameIntro, _body: &Term, _depth: usize) {
+        self.0.insert(intro.label.clone());
+    }
+
+    fn visit_var(&mut self, name: &NameRef, _depth: usize) {
+        self.0.insert(name.label().to_string());
+    }
+
+    fn visit_neu(&mut self, applicant: &NameRef, _arguments: &[Box<Term>], _depth: usize) {
+        self.0.insert(applicant.label().to_string());
+    }
+
+    fn visit_def(&mut self, intro: &NameIntro, _binding: &Term, _body: &Term, _depth: usize) {
+        self.0.insert(intro.label.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ulc::{
+        interpretation::joinable,
+        store::{optimize_sharing, Hash, TermStore},
+        syntax::{term_builder::*, NameIntro, NameRef, Term},
+    };
+
+    #[test]
+    fn test_term_store() {
+        let mut store = TermStore::new();
+        let k = Term::from(lam("x", lam("y", var("x"))));
+        let k_renamed = Term::from(lam("a", lam("b", var("a"))));
+        let hash = store.put(k.clone());
+        assert_eq!(store.put(k_renamed), hash);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get(&hash), Some(&k));
+
+        let k_flipped = Term::from(lam("x", lam("y", var("y"))));
+        let flipped_hash = store.put(k_flipped);
+        assert_ne!(flipped_hash, hash);
+        assert_eq!(store.len(), 2);
+        assert_eq!(
+            Hash::of(&Term::from(lam("z", lam("w", var("w"))))),
+            flipped_hash
+        );
+    }
+
+    #[test]
+    fn test_optimize_sharing() {
+        // λf (f K K K), where K = λx λy x
+        let k = || lam("x", lam("y", var("x")));
+        let term = Term::from(lam("f", neu("f", vec![k(), k(), k()])));
+        let shared = || Term::var(NameRef::new("shared0", 1));
+        let optimized = optimize_sharing(&term);
+        assert_eq!(
+            optimized,
+            Term::def(
+                NameIntro::new("shared0"),
+                k().into(),
+                Term::lam(
+                    NameIntro::new("f"),
+                    Term::neu(NameRef::new("f", 0), vec![shared(), shared(), shared()]),
+                ),
+            )
+        );
+        assert_eq!(optimized.size(), 9);
+        assert!(optimized.size() < term.size());
+        assert_eq!(joinable(&optimized, &term, 100), Ok(true));
+
+        // the shared subterm refers to `f`, so it stays in the scope of `f`
+        let g = || lam("x", neu("f", vec![var("x"), var("x")]));
+        let term = Term::from(lam("f", neu("f", vec![g(), g()])));
+        let optimized = optimize_sharing(&term);
+        assert_eq!(
+            format!("{}", optimized),
+            "λf (def shared0 = λx (f#1 x#0 x#0) in (f#1 shared0#0 shared0#0))"
+        );
+        assert_eq!(joinable(&optimized, &term, 100), Ok(true));
+
+        // sharing `λx x` wouldn't make the term smaller
+        let term = Term::from(lam(
+            "f",
+            neu("f", vec![lam("x", var("x")), lam("x", var("x"))]),
+        ));
+        assert_eq!(optimize_sharing(&term), term);
+    }
+
+    #[test]
+    fn test_app() {
+        // (K K) and (K K), where K = λx λy x
+        let k = || lam("x", lam("y", var("x")));
+        let term = Term::from(app(k(), k()));
+        let mut store = TermStore::new();
+        let hash = store.put(term.clone());
+        assert_eq!(
+            store.put(app(lam("a", lam("b", var("a"))), k()).into()),
+            hash
+        );
+        assert_eq!(store.len(), 1);
+
+        let term = Term::from(lam("f", neu("f", vec![app(k(), k()), app(k(), k())])));
+        let optimized = optimize_sharing(&term);
+        assert!(optimized.size() < term.size());
+        assert_eq!(joinable(&optimized, &term, 100), Ok(true));
+    }
+}
@@ -1,89 +1,1439 @@
 use core::fmt;
-use std::fmt::{Display, Formatter};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    iter::{from_fn, successors},
+    rc::Rc,
+};
+
+use super::interpretation::InterpretError;
 
 // ================================================================================
 /// ## Term
 // ================================================================================
 
-#[derive(Clone, PartialEq, Debug)]
-pub enum Term {
-    Lam {
-        intro: NameIntro,
-        body: Box<Term>,
-    },
-    Neu {
-        applicant: NameRef,
-        arguments: Vec<Box<Term>>,
-    },
-    Def {
-        intro: NameIntro,
-        binding: Box<Term>,
-        body: Box<Term>,
-    },
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Term {
+    Lam {
+        intro: NameIntro,
+        body: Box<Term>,
+    },
+    Var(NameRef),
+    /// An application of a variable to one or more arguments.
+    Neu {
+        applicant: NameRef,
+        arguments: Vec<Box<Term>>,
+    },
+    Def {
+        intro: NameIntro,
+        binding: Box<Term>,
+        body: Box<Term>,
+    },
+    /// An application of any term, such as a lambda, to a single argument.
+    /// [`Term::app`] only builds one if the function isn't a name, so that
+    /// applications of names are `Neu`s, but [`neu_to_app`] rewrites those as
+    /// `App`s too.
+    App {
+        func: Box<Term>,
+        arg: Box<Term>,
+    },
+    /// A term that is left as it is by normalization, apart from its free
+    /// variables, unless it has to be applied.
+    Freeze(Box<Term>),
+    /// A term that is interpreted as soon as it is bound, even in normal
+    /// order.
+    Force(Box<Term>),
+    /// An unknown subterm, such as the part of a Böhm tree that is cut off by
+    /// [`bohm_prefix`]. Interpreting it fails.
+    ///
+    /// [`bohm_prefix`]: super::interpretation::bohm_prefix
+    Hole,
+}
+
+impl Term {
+    /// `λ<NameIntro> <Name>`
+    pub fn lam(intro: NameIntro, body: Term) -> Term {
+        Term::Lam {
+            intro,
+            body: Box::new(body),
+        }
+    }
+
+    /// `λ<NameIntro> ... λ<NameIntro> <Term>`, where the first intro is the
+    /// outermost.
+    pub fn lams_term(intros: Vec<NameIntro>, body: Term) -> Term {
+        intros
+            .into_iter()
+            .rev()
+            .fold(body, |body, intro| Term::lam(intro, body))
+    }
+
+    /// `<Name> <Term> ... <Term>`, which is a `Var` if there are no arguments.
+    pub fn neu(applicant: NameRef, arguments: Vec<Term>) -> Term {
+        if arguments.is_empty() {
+            return Term::Var(applicant);
+        }
+        Term::Neu {
+            applicant,
+            arguments: arguments.into_iter().map(Box::new).collect(),
+        }
+    }
+
+    /// `(<Term> <Term>)`. If `func` is a `Var` or a `Neu` then this is a `Neu`
+    /// with `arg` appended to its arguments, so `app(app(f, a), b)` is
+    /// `(f a b)`, and otherwise it is an `App`.
+    pub fn app(func: Term, arg: Term) -> Term {
+        match func {
+            Term::Var(applicant) => Term::neu(applicant, vec![arg]),
+            Term::Neu {
+                applicant,
+                mut arguments,
+            } => {
+                arguments.push(Box::new(arg));
+                Term::Neu {
+                    applicant,
+                    arguments,
+                }
+            }
+            Term::Lam { .. }
+            | Term::Def { .. }
+            | Term::App { .. }
+            | Term::Freeze(_)
+            | Term::Force(_)
+            | Term::Hole => Term::app_node(func, arg),
+        }
+    }
+
+    /// `(<Term> <Term>)` as an `App`, even if `func` is a `Var` or a `Neu` (see
+    /// [`Term::app`]).
+    pub fn app_node(func: Term, arg: Term) -> Term {
+        Term::App {
+            func: Box::new(func),
+            arg: Box::new(arg),
+        }
+    }
+
+    /// `<Name>`
+    pub fn var(name: NameRef) -> Term {
+        Term::Var(name)
+    }
+
+    /// `def <Name> = <Term> in <Term>`
+    pub fn def(intro: NameIntro, binding: Term, body: Term) -> Term {
+        Term::Def {
+            intro,
+            binding: Box::new(binding),
+            body: Box::new(body),
+        }
+    }
+
+    /// `(⟨frozen⟩ <Term>)`
+    pub fn freeze(term: Term) -> Term {
+        Term::Freeze(Box::new(term))
+    }
+
+    /// `(⟨force⟩ <Term>)`
+    pub fn force(term: Term) -> Term {
+        Term::Force(Box::new(term))
+    }
+
+    /// The number of `Lam`, `Var`, `Neu`, `Def`, `App`, `Freeze`, `Force`, and
+    /// `Hole` nodes in the term. A `Neu` is a single node together with its
+    /// applicant, so `λx (x x)` has size `3`: the `Lam`, the `Neu` `(x x)`,
+    /// and the `Var` `x` in argument position.
+    pub fn size(&self) -> usize {
+        match self {
+            Term::Lam { intro: _, body } => 1 + body.size(),
+            Term::Var(_) => 1,
+            Term::Neu {
+                applicant: _,
+                arguments,
+            } => {
+                1 + arguments
+                    .iter()
+                    .map(|argument| argument.size())
+                    .sum::<usize>()
+            }
+            Term::Def {
+                intro: _,
+                binding,
+                body,
+            } => 1 + binding.size() + body.size(),
+            Term::App { func, arg } => 1 + func.size() + arg.size(),
+            Term::Freeze(term) | Term::Force(term) => 1 + term.size(),
+            Term::Hole => 1,
+        }
+    }
+
+    /// The number of nodes on the longest path from the root of the term to a
+    /// leaf, counting nodes as in [`Term::size`], so a variable has depth `1`.
+    pub fn depth(&self) -> usize {
+        match self {
+            Term::Lam { intro: _, body } => 1 + body.depth(),
+            Term::Var(_) => 1,
+            Term::Neu {
+                applicant: _,
+                arguments,
+            } => {
+                1 + arguments
+                    .iter()
+                    .map(|argument| argument.depth())
+                    .max()
+                    .unwrap_or(0)
+            }
+            Term::Def {
+                intro: _,
+                binding,
+                body,
+            } => 1 + binding.depth().max(body.depth()),
+            Term::App { func, arg } => 1 + func.depth().max(arg.depth()),
+            Term::Freeze(term) | Term::Force(term) => 1 + term.depth(),
+            Term::Hole => 1,
+        }
+    }
+
+    /// The term itself followed by all of its descendants in pre-order, where
+    /// the children of a `Def` are its binding and then its body, and those of
+    /// an `App` are its function and then its argument. This uses an
+    /// explicit stack, so it doesn't overflow the call stack on deep terms.
+    pub fn subterms(&self) -> impl Iterator<Item = &Term> {
+        let mut stack = vec![self];
+        from_fn(move || {
+            let term = stack.pop()?;
+            match term {
+                Term::Lam { intro: _, body } => stack.push(body),
+                Term::Var(_) => {}
+                Term::Neu {
+                    applicant: _,
+                    arguments,
+                } => stack.extend(arguments.iter().rev().map(|argument| argument.as_ref())),
+                Term::Def {
+                    intro: _,
+                    binding,
+                    body,
+                } => {
+                    stack.push(body);
+                    stack.push(binding)
+                }
+                Term::App { func, arg } => {
+                    stack.push(arg);
+                    stack.push(func)
+                }
+                Term::Freeze(term) | Term::Force(term) => stack.push(term),
+                Term::Hole => {}
+            }
+            Some(term)
+        })
+    }
+
+    /// Whether the term is a `Lam`.
+    pub fn is_lam(&self) -> bool {
+        matches!(self, Term::Lam { .. })
+    }
+
+    /// Whether the term is a `Neu`.
+    pub fn is_neu(&self) -> bool {
+        matches!(self, Term::Neu { .. })
+    }
+
+    /// Whether the term is a `Var`.
+    pub fn is_var(&self) -> bool {
+        matches!(self, Term::Var(_))
+    }
+}
+
+/// An arbitrary but stable total order on terms, for sorting and for
+/// `BTreeSet`s. It has no semantic meaning. Terms are ordered first by variant,
+/// in the order they are declared, and then by their children from left to
+/// right, with names compared by index and only then by label, so that the
+/// order agrees with `Eq`.
+impl Ord for Term {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let rank = |term: &Term| match term {
+            Term::Lam { .. } => 0,
+            Term::Var(_) => 1,
+            Term::Neu { .. } => 2,
+            Term::Def { .. } => 3,
+            Term::App { .. } => 4,
+            Term::Freeze(_) => 5,
+            Term::Force(_) => 6,
+            Term::Hole => 7,
+        };
+        let cmp_names =
+            |a: &NameRef, b: &NameRef| a.index.cmp(&b.index).then_with(|| a.label.cmp(&b.label));
+        match (self, other) {
+            (
+                Term::Lam {
+                    intro: intro_a,
+                    body: body_a,
+                },
+                Term::Lam {
+                    intro: intro_b,
+                    body: body_b,
+                },
+            ) => body_a
+                .cmp(body_b)
+                .then_with(|| intro_a.label.cmp(&intro_b.label)),
+            (Term::Var(a), Term::Var(b)) => cmp_names(a, b),
+            (
+                Term::Neu {
+                    applicant: applicant_a,
+                    arguments: arguments_a,
+                },
+                Term::Neu {
+                    applicant: applicant_b,
+                    arguments: arguments_b,
+                },
+            ) => cmp_names(applicant_a, applicant_b).then_with(|| arguments_a.cmp(arguments_b)),
+            (
+                Term::Def {
+                    intro: intro_a,
+                    binding: binding_a,
+                    body: body_a,
+                },
+                Term::Def {
+                    intro: intro_b,
+                    binding: binding_b,
+                    body: body_b,
+                },
+            ) => binding_a
+                .cmp(binding_b)
+                .then_with(|| body_a.cmp(body_b))
+                .then_with(|| intro_a.label.cmp(&intro_b.label)),
+            (
+                Term::App {
+                    func: func_a,
+                    arg: arg_a,
+                },
+                Term::App {
+                    func: func_b,
+                    arg: arg_b,
+                },
+            ) => func_a.cmp(func_b).then_with(|| arg_a.cmp(arg_b)),
+            (Term::Freeze(a), Term::Freeze(b)) | (Term::Force(a), Term::Force(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for Term {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Term::Lam { intro: name, body } => write!(f, "λ{} {}", name, body),
+            Term::Var(name) => write!(f, "{}", name),
+            Term::Neu {
+                applicant,
+                arguments,
+            } => {
+                write!(f, "(")?;
+                write!(f, "{}", applicant)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    // a lambda extends as far to the right as possible
+                    if argument.is_lam() && i + 1 < arguments.len() {
+                        write!(f, " ({})", argument)?;
+                    } else {
+                        write!(f, " {}", argument)?;
+                    }
+                }
+                write!(f, ")")
+            }
+            Term::Def {
+                intro: name,
+                binding,
+                body,
+            } => {
+                write!(f, "(def {} = {} in {})", name, binding, body)
+            }
+            // a lambda in function position extends over the argument unless
+            // it is parenthesized
+            Term::App { func, arg } if func.is_lam() => write!(f, "(({}) {})", func, arg),
+            Term::App { func, arg } => write!(f, "({} {})", func, arg),
+            Term::Freeze(term) => write!(f, "(⟨frozen⟩ {})", term),
+            Term::Force(term) => write!(f, "(⟨force⟩ {})", term),
+            Term::Hole => write!(f, "?"),
+        }
+    }
+}
+
+/// Adds `amount` to the index of every variable in `term` whose index is at
+/// least `cutoff`, where the cutoff is incremented under each binder so that
+/// variables bound within `term` are left unchanged. A negative `amount`
+/// un-shifts the term after a binder is removed, in which case no shifted
+/// index may become negative.
+pub fn shift(term: &Term, cutoff: usize, amount: isize) -> Term {
+    match term {
+        Term::Lam { intro, body } => Term::lam(intro.clone(), shift(body, cutoff + 1, amount)),
+        Term::Var(name) => Term::Var(shift_name(name, cutoff, amount)),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            shift_name(applicant, cutoff, amount),
+            arguments
+                .iter()
+                .map(|argument| shift(argument, cutoff, amount))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(
+            intro.clone(),
+            shift(binding, cutoff, amount),
+            shift(body, cutoff + 1, amount),
+        ),
+        Term::App { func, arg } => {
+            Term::app_node(shift(func, cutoff, amount), shift(arg, cutoff, amount))
+        }
+        Term::Freeze(term) => Term::freeze(shift(term, cutoff, amount)),
+        Term::Force(term) => Term::force(shift(term, cutoff, amount)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+fn shift_name(name: &NameRef, cutoff: usize, amount: isize) -> NameRef {
+    if name.index < cutoff {
+        return name.clone();
+    }
+    let index = name.index as isize + amount;
+    debug_assert!(
+        index >= 0,
+        "shifting `{}` by `{}` makes its index negative",
+        name,
+        amount
+    );
+    NameRef::new(&name.label, index as usize)
+}
+
+/// Replaces the index of every free variable in `term` with the result of
+/// applying `f` to it, where indices are taken relative to the context that
+/// `term` is in (as in [`free_vars`]).
+pub fn reindex(term: &Term, f: &impl Fn(usize) -> usize) -> Term {
+    reindex_at(term, 0, f)
+}
+
+fn reindex_at(term: &Term, depth: usize, f: &impl Fn(usize) -> usize) -> Term {
+    match term {
+        Term::Lam { intro, body } => Term::lam(intro.clone(), reindex_at(body, depth + 1, f)),
+        Term::Var(name) => Term::Var(reindex_name(name, depth, f)),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            reindex_name(applicant, depth, f),
+            arguments
+                .iter()
+                .map(|argument| reindex_at(argument, depth, f))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(
+            intro.clone(),
+            reindex_at(binding, depth, f),
+            reindex_at(body, depth + 1, f),
+        ),
+        Term::App { func, arg } => {
+            Term::app_node(reindex_at(func, depth, f), reindex_at(arg, depth, f))
+        }
+        Term::Freeze(term) => Term::freeze(reindex_at(term, depth, f)),
+        Term::Force(term) => Term::force(reindex_at(term, depth, f)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+fn reindex_name(name: &NameRef, depth: usize, f: &impl Fn(usize) -> usize) -> NameRef {
+    if name.index < depth {
+        return name.clone();
+    }
+    NameRef::new(&name.label, depth + f(name.index - depth))
+}
+
+/// A visitor over the nodes of a `Term`, driven by [`walk`]. Each method is
+/// called with the number of binders that the node is under, and does nothing
+/// by default, so implementors only need to override the cases they care about.
+pub trait TermVisitor {
+    fn visit_lam(&mut self, _intro: &NameIntro, _body: &Term, _depth: usize) {}
+
+    fn visit_var(&mut self, _name: &NameRef, _depth: usize) {}
+
+    fn visit_neu(&mut self, _applicant: &NameRef, _arguments: &[Box<Term>], _depth: usize) {}
+
+    fn visit_def(&mut self, _intro: &NameIntro, _binding: &Term, _body: &Term, _depth: usize) {}
+
+    fn visit_app(&mut self, _func: &Term, _arg: &Term, _depth: usize) {}
+
+    fn visit_freeze(&mut self, _term: &Term, _depth: usize) {}
+
+    fn visit_force(&mut self, _term: &Term, _depth: usize) {}
+
+    fn visit_hole(&mut self, _depth: usize) {}
+}
+
+/// Visits every node of `term` in pre-order, keeping track of how many binders
+/// each node is under. The body of a `def` is under its binder but the binding
+/// isn't.
+pub fn walk<V: TermVisitor>(term: &Term, v: &mut V) {
+    walk_at(term, 0, v)
+}
+
+fn walk_at<V: TermVisitor>(term: &Term, depth: usize, v: &mut V) {
+    match term {
+        Term::Lam { intro, body } => {
+            v.visit_lam(intro, body, depth);
+            walk_at(body, depth + 1, v)
+        }
+        Term::Var(name) => v.visit_var(name, depth),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            v.visit_neu(applicant, arguments, depth);
+            for argument in arguments {
+                walk_at(argument, depth, v)
+            }
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            v.visit_def(intro, binding, body, depth);
+            walk_at(binding, depth, v);
+            walk_at(body, depth + 1, v)
+        }
+        Term::App { func, arg } => {
+            v.visit_app(func, arg, depth);
+            walk_at(func, depth, v);
+            walk_at(arg, depth, v)
+        }
+        Term::Freeze(term) => {
+            v.visit_freeze(term, depth);
+            walk_at(term, depth, v)
+        }
+        Term::Force(term) => {
+            v.visit_force(term, depth);
+            walk_at(term, depth, v)
+        }
+        Term::Hole => v.visit_hole(depth),
+    }
+}
+
+/// The variables that occur free in `term`, with their indices adjusted to
+/// refer to the context that `term` is in. Each variable is listed once, in
+/// the order of its first occurrence.
+pub fn free_vars(term: &Term) -> Vec<NameRef> {
+    let mut free = FreeVars(vec![]);
+    walk(term, &mut free);
+    free.0
+}
+
+struct FreeVars(Vec<NameRef>);
+
+impl FreeVars {
+    fn collect(&mut self, name: &NameRef, depth: usize) {
+        if name.index >= depth {
+            let name = NameRef::new(&name.label, name.index - depth);
+            if !self.0.contains(&name) {
+                self.0.push(name)
+            }
+        }
+    }
+}
+
+impl TermVisitor for FreeVars {
+    fn visit_var(&mut self, name: &NameRef, depth: usize) {
+        self.collect(name, depth)
+    }
+
+    fn visit_neu(&mut self, applicant: &NameRef, _arguments: &[Box<Term>], depth: usize) {
+        self.collect(applicant, depth)
+    }
+}
+
+/// Splits the leading chain of `def`s off of `term`, returning each `def`'s
+/// intro and binding in order along with the body of the last one.
+pub fn collect_defs(term: &Term) -> (Vec<(&NameIntro, &Term)>, &Term) {
+    let mut defs = vec![];
+    let mut term = term;
+    while let Term::Def {
+        intro,
+        binding,
+        body,
+    } = term
+    {
+        defs.push((intro, binding.as_ref()));
+        term = body;
+    }
+    (defs, term)
+}
+
+/// Whether `a` and `b` are equal up to the labels of their binders and
+/// variables, i.e. they have the same structure and de Bruijn indices.
+pub fn alpha_eq(a: &Term, b: &Term) -> bool {
+    match (a, b) {
+        (Term::Lam { intro: _, body: a }, Term::Lam { intro: _, body: b }) => alpha_eq(a, b),
+        (Term::Var(a), Term::Var(b)) => a.index == b.index,
+        (
+            Term::Neu {
+                applicant: a_applicant,
+                arguments: a_arguments,
+            },
+            Term::Neu {
+                applicant: b_applicant,
+                arguments: b_arguments,
+            },
+        ) => {
+            a_applicant.index == b_applicant.index
+                && a_arguments.len() == b_arguments.len()
+                && a_arguments
+                    .iter()
+                    .zip(b_arguments.iter())
+                    .all(|(a, b)| alpha_eq(a, b))
+        }
+        (
+            Term::Def {
+                intro: _,
+                binding: a_binding,
+                body: a_body,
+            },
+            Term::Def {
+                intro: _,
+                binding: b_binding,
+                body: b_body,
+            },
+        ) => alpha_eq(a_binding, b_binding) && alpha_eq(a_body, b_body),
+        (
+            Term::App {
+                func: a_func,
+                arg: a_arg,
+            },
+            Term::App {
+                func: b_func,
+                arg: b_arg,
+            },
+        ) => alpha_eq(a_func, b_func) && alpha_eq(a_arg, b_arg),
+        (Term::Freeze(a), Term::Freeze(b)) | (Term::Force(a), Term::Force(b)) => alpha_eq(a, b),
+        _ => false,
+    }
+}
+
+/// Converts `term` to A-normal form, where every argument of a `Neu` is a
+/// variable. Each non-variable argument is bound by a fresh `def` (labelled
+/// `anf<n>`) that is introduced just before the application, and `def`s nested
+/// in bindings are flattened into the enclosing `def` chain.
+pub fn to_anf(term: &Term) -> Term {
+    let mut fresh = FreshLabels::new(term);
+    anf(term, &mut fresh)
+}
+
+fn anf(term: &Term, fresh: &mut FreshLabels) -> Term {
+    let (chain, tail) = anf_chain(term, fresh);
+    chain
+        .into_iter()
+        .rev()
+        .fold(tail, |body, (intro, binding)| {
+            Term::def(intro, binding, body)
+        })
+}
+
+/// Returns a `def` chain and a tail term that is in the scope of the whole
+/// chain, such that `def <chain> in <tail>` is the A-normal form of `term`.
+fn anf_chain(term: &Term, fresh: &mut FreshLabels) -> (Vec<(NameIntro, Term)>, Term) {
+    match term {
+        Term::Lam { intro, body } => (vec![], Term::lam(intro.clone(), anf(body, fresh))),
+        Term::Var(name) => (vec![], Term::Var(name.clone())),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let mut chain = vec![];
+            // each argument is recorded along with the length of the chain at
+            // the point it was recorded, so that it can be shifted over the
+            // rest of the chain afterwards
+            let mut trivial_arguments = vec![];
+            for argument in arguments {
+                let argument = shift(argument, 0, chain.len() as isize);
+                if argument.is_var() {
+                    trivial_arguments.push((chain.len(), argument));
+                    continue;
+                }
+                let (argument_chain, argument) = anf_chain(&argument, fresh);
+                chain.extend(argument_chain);
+                if argument.is_var() {
+                    trivial_arguments.push((chain.len(), argument));
+                } else {
+                    let intro = fresh.next();
+                    chain.push((intro.clone(), argument));
+                    trivial_arguments.push((chain.len(), Term::var(NameRef::new(&intro.label, 0))));
+                }
+            }
+            let depth = chain.len();
+            let tail = Term::neu(
+                NameRef::new(&applicant.label, applicant.index + depth),
+                trivial_arguments
+                    .into_iter()
+                    .map(|(recorded_depth, argument)| {
+                        shift(&argument, 0, (depth - recorded_depth) as isize)
+                    })
+                    .collect(),
+            );
+            (chain, tail)
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            let (mut chain, binding) = anf_chain(binding, fresh);
+            let body = shift(body, 1, chain.len() as isize);
+            chain.push((intro.clone(), binding));
+            let (body_chain, body) = anf_chain(&body, fresh);
+            chain.extend(body_chain);
+            (chain, body)
+        }
+        // the function is named by a `def` so that the application becomes a
+        // `Neu`
+        Term::App { func, arg } => {
+            let intro = fresh.next();
+            let applicant = NameRef::new(&intro.label, 0);
+            anf_chain(
+                &Term::def(
+                    intro,
+                    func.as_ref().clone(),
+                    Term::neu(applicant, vec![shift(arg, 0, 1)]),
+                ),
+                fresh,
+            )
+        }
+        // the `def`s within a marked term are not flattened out of it, so that
+        // they are still under the marker
+        Term::Freeze(term) => (vec![], Term::freeze(anf(term, fresh))),
+        Term::Force(term) => (vec![], Term::force(anf(term, fresh))),
+        Term::Hole => (vec![], Term::Hole),
+    }
+}
+
+const ADMIN_LABEL_PREFIX: &str = "anf";
+
+/// Generates labels of the form `anf<n>` that don't occur in a given term.
+struct FreshLabels {
+    used: HashSet<String>,
+    counter: usize,
+}
+
+impl FreshLabels {
+    fn new(term: &Term) -> FreshLabels {
+        let mut used = HashSet::new();
+        collect_labels(term, &mut used);
+        FreshLabels { used, counter: 0 }
+    }
+
+    fn next(&mut self) -> NameIntro {
+        loop {
+            let label = format!("{}{}", ADMIN_LABEL_PREFIX, self.counter);
+            self.counter += 1;
+            if !self.used.contains(&label) {
+                return NameIntro::new(&label);
+            }
+        }
+    }
+}
+
+fn collect_labels(term: &Term, labels: &mut HashSet<String>) {
+    match term {
+        Term::Lam { intro, body } => {
+            labels.insert(intro.label.clone());
+            collect_labels(body, labels)
+        }
+        Term::Var(name) => {
+            labels.insert(name.label.clone());
+        }
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            labels.insert(applicant.label.clone());
+            for argument in arguments {
+                collect_labels(argument, labels)
+            }
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            labels.insert(intro.label.clone());
+            collect_labels(binding, labels);
+            collect_labels(body, labels)
+        }
+        Term::App { func, arg } => {
+            collect_labels(func, labels);
+            collect_labels(arg, labels)
+        }
+        Term::Freeze(term) | Term::Force(term) => collect_labels(term, labels),
+        Term::Hole => {}
+    }
+}
+
+/// Whether `label` has the form of the labels that [`to_anf`] introduces.
+fn is_admin_label(label: &str) -> bool {
+    label
+        .strip_prefix(ADMIN_LABEL_PREFIX)
+        .is_some_and(|counter| !counter.is_empty() && counter.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Compares two terms with [`alpha_eq`] after inlining, on both sides, every
+/// administrative `def` (one whose label has the form that [`to_anf`]
+/// introduces) that is used exactly once in its body. An administrative `def`
+/// whose single use is the head of a `Neu` with arguments is only inlined if
+/// its binding is itself a `Neu`, since [`subst`] would otherwise bind the
+/// binding by a `def` again to keep the `Neu` headed by a name.
+pub fn eq_modulo_admin(a: &Term, b: &Term) -> bool {
+    alpha_eq(&inline_admin(a), &inline_admin(b))
+}
+
+fn inline_admin(term: &Term) -> Term {
+    match term {
+        Term::Lam { intro, body } => Term::lam(intro.clone(), inline_admin(body)),
+        Term::Var(name) => Term::Var(name.clone()),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            applicant.clone(),
+            arguments
+                .iter()
+                .map(|argument| inline_admin(argument))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            let binding = inline_admin(binding);
+            let body = inline_admin(body);
+            if is_admin_label(&intro.label)
+                && count_uses(&body, 0) == 1
+                && (binding.is_neu() || !is_applied(&body, 0))
+            {
+                return shift(&subst(&body, 0, &shift(&binding, 0, 1)), 0, -1);
+            }
+            Term::def(intro.clone(), binding, body)
+        }
+        Term::App { func, arg } => Term::app_node(inline_admin(func), inline_admin(arg)),
+        Term::Freeze(term) => Term::freeze(inline_admin(term)),
+        Term::Force(term) => Term::force(inline_admin(term)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+/// Renames every binder in `term` to a label that no other binder or free
+/// variable in it has, by appending a counter to its label, and renames the
+/// variables bound by it to match. The indices are unchanged, so the result is
+/// [`alpha_eq`] to `term`.
+pub fn freshen(term: &Term) -> Term {
+    let mut used = HashSet::new();
+    collect_labels(term, &mut used);
+    freshen_in(term, &mut vec![], &mut used, &mut 0)
+}
+
+/// Freshens `term` under binders whose new labels are `ctx`, innermost last.
+fn freshen_in(
+    term: &Term,
+    ctx: &mut Vec<String>,
+    used: &mut HashSet<String>,
+    counter: &mut usize,
+) -> Term {
+    let mut fresh = |intro: &NameIntro| loop {
+        let label = format!("{}{}", intro.label, counter);
+        *counter += 1;
+        if used.insert(label.clone()) {
+            return label;
+        }
+    };
+    let rename = |name: &NameRef, ctx: &Vec<String>| match ctx.len().checked_sub(name.index + 1) {
+        Some(level) => NameRef::new(&ctx[level], name.index),
+        None => name.clone(),
+    };
+    match term {
+        Term::Lam { intro, body } => {
+            let label = fresh(intro);
+            ctx.push(label.clone());
+            let body = freshen_in(body, ctx, used, counter);
+            ctx.pop();
+            Term::lam(NameIntro::new(&label), body)
+        }
+        Term::Var(name) => Term::Var(rename(name, ctx)),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            rename(applicant, ctx),
+            arguments
+                .iter()
+                .map(|argument| freshen_in(argument, ctx, used, counter))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            let label = fresh(intro);
+            let binding = freshen_in(binding, ctx, used, counter);
+            ctx.push(label.clone());
+            let body = freshen_in(body, ctx, used, counter);
+            ctx.pop();
+            Term::def(NameIntro::new(&label), binding, body)
+        }
+        Term::App { func, arg } => Term::app_node(
+            freshen_in(func, ctx, used, counter),
+            freshen_in(arg, ctx, used, counter),
+        ),
+        Term::Freeze(term) => Term::freeze(freshen_in(term, ctx, used, counter)),
+        Term::Force(term) => Term::force(freshen_in(term, ctx, used, counter)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+/// The intros of the lambdas and `def`s in `term` whose variable is never
+/// referred to, in pre-order. Since references are resolved by index, a use of
+/// an inner binder with the same label doesn't count as a use of an outer one.
+pub fn unused_bindings(term: &Term) -> Vec<NameIntro> {
+    let mut unused = UnusedBindings(vec![]);
+    walk(term, &mut unused);
+    unused.0
+}
+
+struct UnusedBindings(Vec<NameIntro>);
+
+impl TermVisitor for UnusedBindings {
+    fn visit_lam(&mut self, intro: &NameIntro, body: &Term, _depth: usize) {
+        if count_uses(body, 0) == 0 {
+            self.0.push(intro.clone())
+        }
+    }
+
+    fn visit_def(&mut self, intro: &NameIntro, _binding: &Term, body: &Term, _depth: usize) {
+        if count_uses(body, 0) == 0 {
+            self.0.push(intro.clone())
+        }
+    }
+}
+
+/// The number of occurrences of the variable at `index` in `term`.
+fn count_uses(term: &Term, index: usize) -> usize {
+    match term {
+        Term::Lam { intro: _, body } => count_uses(body, index + 1),
+        Term::Var(name) => usize::from(name.index == index),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            usize::from(applicant.index == index)
+                + arguments
+                    .iter()
+                    .map(|argument| count_uses(argument, index))
+                    .sum::<usize>()
+        }
+        Term::Def {
+            intro: _,
+            binding,
+            body,
+        } => count_uses(binding, index) + count_uses(body, index + 1),
+        Term::App { func, arg } => count_uses(func, index) + count_uses(arg, index),
+        Term::Freeze(term) | Term::Force(term) => count_uses(term, index),
+        Term::Hole => 0,
+    }
+}
+
+/// Whether the variable at `index` is the head of a `Neu` or the function of an
+/// `App` in `term`.
+fn is_applied(term: &Term, index: usize) -> bool {
+    match term {
+        Term::Lam { intro: _, body } => is_applied(body, index + 1),
+        Term::Var(_) => false,
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            applicant.index == index || arguments.iter().any(|argument| is_applied(argument, index))
+        }
+        Term::Def {
+            intro: _,
+            binding,
+            body,
+        } => is_applied(binding, index) || is_applied(body, index + 1),
+        Term::App { func, arg } => {
+            matches!(func.as_ref(), Term::Var(name) if name.index == index)
+                || is_applied(func, index)
+                || is_applied(arg, index)
+        }
+        Term::Freeze(term) | Term::Force(term) => is_applied(term, index),
+        Term::Hole => false,
+    }
 }
 
-impl Term {
-    /// `λ<NameIntro> <Name>`
-    pub fn lam(intro: NameIntro, body: Term) -> Term {
-        Term::Lam {
+/// Substitutes `replacement` for the variable at `index` in `term`, shifting
+/// `replacement` by one as it goes under each binder. Other variables are left
+/// unchanged, so to reduce `(λx b) a` the binder has to be removed with
+/// `shift(&subst(b, 0, &shift(a, 0, 1)), 0, -1)`.
+///
+/// Since only a name can be the head of a `Neu`, an occurrence of the variable
+/// that is applied to arguments is handled specially: if `replacement` is a
+/// `Var` or `Neu` then the arguments are appended to it, and otherwise it is
+/// bound by a `def` that takes the variable's label, as in `def x =
+/// <replacement> in (x ...)`.
+pub fn subst(term: &Term, index: usize, replacement: &Term) -> Term {
+    match term {
+        Term::Lam { intro, body } => Term::lam(
+            intro.clone(),
+            subst(body, index + 1, &shift(replacement, 0, 1)),
+        ),
+        Term::Var(name) if name.index == index => replacement.clone(),
+        Term::Var(name) => Term::Var(name.clone()),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let arguments: Vec<Term> = arguments
+                .iter()
+                .map(|argument| subst(argument, index, replacement))
+                .collect();
+            if applicant.index != index {
+                Term::neu(applicant.clone(), arguments)
+            } else if let Term::Var(replacement) = replacement {
+                Term::neu(replacement.clone(), arguments)
+            } else if let Term::Neu {
+                applicant: replacement_applicant,
+                arguments: replacement_arguments,
+            } = replacement
+            {
+                Term::neu(
+                    replacement_applicant.clone(),
+                    replacement_arguments
+                        .iter()
+                        .map(|argument| argument.as_ref().clone())
+                        .chain(arguments)
+                        .collect(),
+                )
+            } else {
+                Term::def(
+                    NameIntro::new(&applicant.label),
+                    replacement.clone(),
+                    Term::neu(
+                        NameRef::new(&applicant.label, 0),
+                        arguments
+                            .iter()
+                            .map(|argument| shift(argument, 0, 1))
+                            .collect(),
+                    ),
+                )
+            }
+        }
+        Term::Def {
             intro,
-            body: Box::new(body),
+            binding,
+            body,
+        } => Term::def(
+            intro.clone(),
+            subst(binding, index, replacement),
+            subst(body, index + 1, &shift(replacement, 0, 1)),
+        ),
+        Term::App { func, arg } => Term::app_node(
+            subst(func, index, replacement),
+            subst(arg, index, replacement),
+        ),
+        Term::Freeze(term) => Term::freeze(subst(term, index, replacement)),
+        Term::Force(term) => Term::force(subst(term, index, replacement)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+/// Eta-reduces every lambda in `term` of the form `λx (f ... x)`, where `x`
+/// doesn't occur in `f ...`, to `(f ...)`, and likewise for an `App` whose
+/// argument is `x`. The body of a lambda is reduced first, so `λx λy (f x y)`
+/// is reduced all the way to `f`.
+pub fn eta_reduce(term: &Term) -> Term {
+    match term {
+        Term::Lam { intro, body } => {
+            let body = eta_reduce(body);
+            eta_contract(&body).unwrap_or_else(|| Term::lam(intro.clone(), body))
         }
+        Term::Var(name) => Term::Var(name.clone()),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => Term::neu(
+            applicant.clone(),
+            arguments
+                .iter()
+                .map(|argument| eta_reduce(argument))
+                .collect(),
+        ),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(intro.clone(), eta_reduce(binding), eta_reduce(body)),
+        Term::App { func, arg } => Term::app_node(eta_reduce(func), eta_reduce(arg)),
+        Term::Freeze(term) => Term::freeze(eta_reduce(term)),
+        Term::Force(term) => Term::force(eta_reduce(term)),
+        Term::Hole => Term::Hole,
     }
+}
 
-    /// `<Name> <Term> ... <Term>`
-    pub fn neu(applicant: NameRef, arguments: Vec<Term>) -> Term {
+/// The eta-contraction of a lambda with body `body`, if it has the form
+/// `λx (f ... x)` or `λx (e x)` where `x` doesn't occur in `f ...` or `e`.
+fn eta_contract(body: &Term) -> Option<Term> {
+    let is_bound = |term: &Term| free_vars(term).iter().any(|name| name.index == 0);
+    match body {
         Term::Neu {
             applicant,
-            arguments: arguments.into_iter().map(Box::new).collect(),
+            arguments,
+        } => {
+            let (last, rest) = arguments.split_last()?;
+            if matches!(last.as_ref(), Term::Var(name) if name.index == 0)
+                && applicant.index != 0
+                && !rest.iter().any(|argument| is_bound(argument))
+            {
+                Some(Term::neu(
+                    shift_name(applicant, 0, -1),
+                    rest.iter().map(|argument| shift(argument, 0, -1)).collect(),
+                ))
+            } else {
+                None
+            }
+        }
+        Term::App { func, arg } => {
+            if matches!(arg.as_ref(), Term::Var(name) if name.index == 0) && !is_bound(func) {
+                Some(shift(func, 0, -1))
+            } else {
+                None
+            }
         }
+        Term::Lam { .. }
+        | Term::Var(_)
+        | Term::Def { .. }
+        | Term::Freeze(_)
+        | Term::Force(_)
+        | Term::Hole => None,
     }
+}
 
-    /// `<Name>`
-    pub fn var(name: NameRef) -> Term {
+/// Reduces the leftmost-outermost beta redex `(λx b) a` in `term`, an `App`
+/// whose function is a lambda, to `b` with `a` substituted for `x`. Returns
+/// `None` if `term` has no beta redex.
+pub fn beta_step(term: &Term) -> Option<Term> {
+    leftmost_step(term, &|term| match term {
+        Term::App { func, arg } => match func.as_ref() {
+            Term::Lam { body, .. } => Some(instantiate(body, arg)),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Contracts the leftmost-outermost eta redex `λx (f ... x)` or `λx (e x)`,
+/// where `x` doesn't occur in `f ...` or `e`, in `term` (see [`eta_reduce`]).
+/// Returns `None` if `term` has no eta redex.
+pub fn eta_step(term: &Term) -> Option<Term> {
+    leftmost_step(term, &|term| match term {
+        Term::Lam { body, .. } => eta_contract(body),
+        _ => None,
+    })
+}
+
+/// Unfolds the leftmost-outermost `def x = e in b` in `term` to `b` with `e`
+/// substituted for `x`. Returns `None` if `term` has no `def`.
+pub fn delta_step(term: &Term) -> Option<Term> {
+    leftmost_step(term, &|term| match term {
+        Term::Def { binding, body, .. } => Some(instantiate(body, binding)),
+        _ => None,
+    })
+}
+
+/// Substitutes `arg` for the variable bound at index `0` in `body`, removing
+/// the binder. If `arg` isn't a name or a `Neu`, then each application of the
+/// variable becomes nested `App`s of `arg`, so that `(λx (x a)) (λy y)`
+/// reduces to `((λy y) a)` rather than to `def x = λy y in (x a)` like
+/// [`subst`] would make it.
+fn instantiate(body: &Term, arg: &Term) -> Term {
+    let body = match arg {
+        Term::Var(_) | Term::Neu { .. } => body.clone(),
+        _ => unapply(body, 0),
+    };
+    shift(&subst(&body, 0, &shift(arg, 0, 1)), 0, -1)
+}
+
+/// Rewrites each `Neu` in `term` whose applicant is the variable at `index` as
+/// nested `App`s of that variable (see [`neu_to_app`]).
+fn unapply(term: &Term, index: usize) -> Term {
+    match term {
+        Term::Lam { intro, body } => Term::lam(intro.clone(), unapply(body, index + 1)),
+        Term::Var(name) => Term::Var(name.clone()),
         Term::Neu {
-            applicant: name,
-            arguments: vec![],
+            applicant,
+            arguments,
+        } => {
+            let arguments = arguments.iter().map(|argument| unapply(argument, index));
+            if applicant.index == index {
+                arguments.fold(Term::Var(applicant.clone()), Term::app_node)
+            } else {
+                Term::neu(applicant.clone(), arguments.collect())
+            }
         }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(
+            intro.clone(),
+            unapply(binding, index),
+            unapply(body, index + 1),
+        ),
+        Term::App { func, arg } => Term::app_node(unapply(func, index), unapply(arg, index)),
+        Term::Freeze(term) => Term::freeze(unapply(term, index)),
+        Term::Force(term) => Term::force(unapply(term, index)),
+        Term::Hole => Term::Hole,
     }
+}
 
-    /// `def <Name> = <Term> in <Term>`
-    pub fn def(intro: NameIntro, binding: Term, body: Term) -> Term {
+/// Rewrites the leftmost-outermost subterm of `term` that `rule` applies to.
+fn leftmost_step(term: &Term, rule: &impl Fn(&Term) -> Option<Term>) -> Option<Term> {
+    if let Some(reduct) = rule(term) {
+        return Some(reduct);
+    }
+    match term {
+        Term::Lam { intro, body } => {
+            leftmost_step(body, rule).map(|body| Term::lam(intro.clone(), body))
+        }
+        Term::Var(_) => None,
+        Term::Neu {
+            applicant,
+            arguments,
+        } => arguments.iter().enumerate().find_map(|(i, argument)| {
+            let argument = leftmost_step(argument, rule)?;
+            let mut arguments: Vec<Term> = arguments
+                .iter()
+                .map(|argument| argument.as_ref().clone())
+                .collect();
+            arguments[i] = argument;
+            Some(Term::neu(applicant.clone(), arguments))
+        }),
         Term::Def {
             intro,
-            binding: Box::new(binding),
-            body: Box::new(body),
+            binding,
+            body,
+        } => match leftmost_step(binding, rule) {
+            Some(binding) => Some(Term::def(intro.clone(), binding, body.as_ref().clone())),
+            None => leftmost_step(body, rule)
+                .map(|body| Term::def(intro.clone(), binding.as_ref().clone(), body)),
+        },
+        Term::App { func, arg } => match leftmost_step(func, rule) {
+            Some(func) => Some(Term::app_node(func, arg.as_ref().clone())),
+            None => leftmost_step(arg, rule).map(|arg| Term::app_node(func.as_ref().clone(), arg)),
+        },
+        Term::Freeze(term) => leftmost_step(term, rule).map(Term::freeze),
+        Term::Force(term) => leftmost_step(term, rule).map(Term::force),
+        Term::Hole => None,
+    }
+}
+
+/// Rewrites every `Neu` in `term` as nested `App`s of a `Var`, so that
+/// `(f a b)` becomes `((f a) b)`. The result has the same normal form (see
+/// [`normalize`]).
+///
+/// [`normalize`]: super::interpretation::normalize
+pub fn neu_to_app(term: &Term) -> Term {
+    match term {
+        Term::Lam { intro, body } => Term::lam(intro.clone(), neu_to_app(body)),
+        Term::Var(name) => Term::Var(name.clone()),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => arguments
+            .iter()
+            .fold(Term::Var(applicant.clone()), |func, argument| {
+                Term::app_node(func, neu_to_app(argument))
+            }),
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => Term::def(intro.clone(), neu_to_app(binding), neu_to_app(body)),
+        Term::App { func, arg } => Term::app_node(neu_to_app(func), neu_to_app(arg)),
+        Term::Freeze(term) => Term::freeze(neu_to_app(term)),
+        Term::Force(term) => Term::force(neu_to_app(term)),
+        Term::Hole => Term::Hole,
+    }
+}
+
+/// Compares two terms with [`alpha_eq`] after rewriting both with
+/// [`neu_to_app`], so that an application written as a single `Neu`, like
+/// `(f a b)`, equals the same application curried with `App`s, like
+/// `((f a) b)`.
+pub fn eq_modulo_currying(a: &Term, b: &Term) -> bool {
+    alpha_eq(&neu_to_app(a), &neu_to_app(b))
+}
+
+/// The binders in `term` whose label is the same as that of an enclosing
+/// binder, along with the number of binders enclosing each of them.
+pub fn shadowed_names(term: &Term) -> Vec<(NameIntro, usize)> {
+    let mut shadowed = vec![];
+    collect_shadowed_names(&mut vec![], term, &mut shadowed);
+    shadowed
+}
+
+fn collect_shadowed_names(
+    ctx: &mut Vec<String>,
+    term: &Term,
+    shadowed: &mut Vec<(NameIntro, usize)>,
+) {
+    match term {
+        Term::Lam { intro, body } => {
+            if ctx.contains(&intro.label) {
+                shadowed.push((intro.clone(), ctx.len()))
+            }
+            ctx.push(intro.label.clone());
+            collect_shadowed_names(ctx, body, shadowed);
+            ctx.pop();
+        }
+        Term::Var(_) => {}
+        Term::Neu {
+            applicant: _,
+            arguments,
+        } => {
+            for argument in arguments {
+                collect_shadowed_names(ctx, argument, shadowed)
+            }
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            collect_shadowed_names(ctx, binding, shadowed);
+            if ctx.contains(&intro.label) {
+                shadowed.push((intro.clone(), ctx.len()))
+            }
+            ctx.push(intro.label.clone());
+            collect_shadowed_names(ctx, body, shadowed);
+            ctx.pop();
         }
+        Term::App { func, arg } => {
+            collect_shadowed_names(ctx, func, shadowed);
+            collect_shadowed_names(ctx, arg, shadowed)
+        }
+        Term::Freeze(term) | Term::Force(term) => collect_shadowed_names(ctx, term, shadowed),
+        Term::Hole => {}
     }
 }
 
-impl Display for Term {
+// ================================================================================
+/// ## ScopeError
+// ================================================================================
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ScopeError {
+    /// The variable's index is past all the binders enclosing it.
+    Unbound { name: NameRef, depth: usize },
+    /// The binder at the variable's index has a different label.
+    LabelMismatch { name: NameRef, binder: NameIntro },
+}
+
+impl Display for ScopeError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
-            Term::Lam { intro: name, body } => write!(f, "λ{} {}", name, body),
-            Term::Neu {
-                applicant,
-                arguments,
-            } => {
-                if arguments.is_empty() {
-                    write!(f, "{}", applicant)
-                } else {
-                    write!(f, "(")?;
-                    write!(f, "{}", applicant)?;
-                    for argument in arguments.iter() {
-                        write!(f, " {}", argument)?;
-                    }
-                    write!(f, ")")
-                }
-            }
-            Term::Def {
-                intro: name,
-                binding,
-                body,
-            } => {
-                write!(f, "(def {} = {} in {})", name, binding, body)
+            ScopeError::Unbound { name, depth } => write!(
+                f,
+                "the variable `{}` refers past the `{}` binders enclosing it",
+                name, depth
+            ),
+            ScopeError::LabelMismatch { name, binder } => write!(
+                f,
+                "the variable `{}` refers to the binder `{}` with a different label",
+                name, binder
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScopeError {}
+
+/// Checks that every variable in the closed term `term` refers to an enclosing
+/// binder with the same label, returning every variable that doesn't, in
+/// pre-order.
+pub fn check_scopes(term: &Term) -> Result<(), Vec<ScopeError>> {
+    let mut errors = vec![];
+    collect_scope_errors(&mut vec![], term, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn collect_scope_errors(ctx: &mut Vec<NameIntro>, term: &Term, errors: &mut Vec<ScopeError>) {
+    let mut check = |name: &NameRef, ctx: &Vec<NameIntro>| match ctx
+        .len()
+        .checked_sub(name.index + 1)
+    {
+        None => errors.push(ScopeError::Unbound {
+            name: name.clone(),
+            depth: ctx.len(),
+        }),
+        Some(level) if ctx[level].label != name.label => errors.push(ScopeError::LabelMismatch {
+            name: name.clone(),
+            binder: ctx[level].clone(),
+        }),
+        Some(_) => {}
+    };
+    match term {
+        Term::Lam { intro, body } => {
+            ctx.push(intro.clone());
+            collect_scope_errors(ctx, body, errors);
+            ctx.pop();
+        }
+        Term::Var(name) => check(name, ctx),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            check(applicant, ctx);
+            for argument in arguments {
+                collect_scope_errors(ctx, argument, errors)
             }
         }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => {
+            collect_scope_errors(ctx, binding, errors);
+            ctx.push(intro.clone());
+            collect_scope_errors(ctx, body, errors);
+            ctx.pop();
+        }
+        Term::App { func, arg } => {
+            collect_scope_errors(ctx, func, errors);
+            collect_scope_errors(ctx, arg, errors)
+        }
+        Term::Freeze(term) | Term::Force(term) => collect_scope_errors(ctx, term, errors),
+        Term::Hole => {}
     }
 }
 
@@ -92,6 +1442,7 @@ impl Display for Term {
 // ================================================================================
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TermBuilder {
     Lam {
         name: String,
@@ -106,6 +1457,57 @@ pub enum TermBuilder {
         binding: Box<TermBuilder>,
         body: Box<TermBuilder>,
     },
+    App {
+        func: Box<TermBuilder>,
+        arg: Box<TermBuilder>,
+    },
+    Freeze(Box<TermBuilder>),
+    Force(Box<TermBuilder>),
+    Hole,
+}
+
+/// Renders like the `Display` impl of `Term`, but with the names as given, each
+/// followed by `@n` only if it has the explicit index `n`.
+impl Display for TermBuilder {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TermBuilder::Lam { name, body } => write!(f, "λ{} {}", name, body),
+            TermBuilder::Neu {
+                applicant,
+                arguments,
+            } => {
+                let applicant = match applicant {
+                    (name, Some(index)) => format!("{}@{}", name, index),
+                    (name, None) => name.clone(),
+                };
+                if arguments.is_empty() {
+                    return write!(f, "{}", applicant);
+                }
+                write!(f, "({}", applicant)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    // a lambda extends as far to the right as possible
+                    if matches!(**argument, TermBuilder::Lam { .. }) && i + 1 < arguments.len() {
+                        write!(f, " ({})", argument)?;
+                    } else {
+                        write!(f, " {}", argument)?;
+                    }
+                }
+                write!(f, ")")
+            }
+            TermBuilder::Def {
+                name,
+                binding,
+                body,
+            } => write!(f, "(def {} = {} in {})", name, binding, body),
+            TermBuilder::App { func, arg } if matches!(**func, TermBuilder::Lam { .. }) => {
+                write!(f, "(({}) {})", func, arg)
+            }
+            TermBuilder::App { func, arg } => write!(f, "({} {})", func, arg),
+            TermBuilder::Freeze(term) => write!(f, "(⟨frozen⟩ {})", term),
+            TermBuilder::Force(term) => write!(f, "(⟨force⟩ {})", term),
+            TermBuilder::Hole => write!(f, "?"),
+        }
+    }
 }
 
 pub mod term_builder {
@@ -118,6 +1520,11 @@ pub mod term_builder {
         }
     }
 
+    /// `λ<name> ... λ<name> <body>`, where the first name is the outermost.
+    pub fn lams(names: &[&str], body: TermBuilder) -> TermBuilder {
+        names.iter().rev().fold(body, |body, name| lam(name, body))
+    }
+
     pub fn neu(applicant: &str, arguments: Vec<TermBuilder>) -> TermBuilder {
         TermBuilder::Neu {
             applicant: (applicant.to_string(), None),
@@ -152,9 +1559,50 @@ pub mod term_builder {
         }
     }
 
+    /// `(<func> <arg>)` as an `App`, for applying a term that isn't a name.
+    pub fn app(func: TermBuilder, arg: TermBuilder) -> TermBuilder {
+        TermBuilder::App {
+            func: Box::new(func),
+            arg: Box::new(arg),
+        }
+    }
+
+    /// `(<head> <arg> ... <arg>)` the way [`parse`] reads it: a `Neu` with the
+    /// arguments appended to its own if `head` is one, and otherwise nested
+    /// `App`s of `head`.
+    ///
+    /// [`parse`]: crate::ulc::parser::parse
+    pub fn apps(head: TermBuilder, arguments: Vec<TermBuilder>) -> TermBuilder {
+        match head {
+            TermBuilder::Neu {
+                applicant,
+                arguments: mut head_arguments,
+            } => {
+                head_arguments.extend(arguments.into_iter().map(Box::new));
+                TermBuilder::Neu {
+                    applicant,
+                    arguments: head_arguments,
+                }
+            }
+            head => arguments.into_iter().fold(head, app),
+        }
+    }
+
+    pub fn freeze(term: TermBuilder) -> TermBuilder {
+        TermBuilder::Freeze(Box::new(term))
+    }
+
+    pub fn force(term: TermBuilder) -> TermBuilder {
+        TermBuilder::Force(Box::new(term))
+    }
+
+    pub fn hole() -> TermBuilder {
+        TermBuilder::Hole
+    }
+
     #[cfg(test)]
     mod tests {
-        use crate::ulc::syntax::{NameIntro, NameRef, Term};
+        use crate::ulc::syntax::{resolve, NameIntro, NameRef, Term};
 
         use super::*;
 
@@ -173,27 +1621,158 @@ pub mod term_builder {
                 )
             );
             assert_eq!(
-                Term::from(def("x", lam("y", var("y")), neu("x", vec![var("x")]))),
-                Term::def(
-                    NameIntro::new("x"),
-                    Term::lam(NameIntro::new("y"), Term::var(NameRef::new("y", 0))),
-                    Term::neu(NameRef::new("x", 0), vec![Term::var(NameRef::new("x", 0))])
-                )
+                Term::from(def("x", lam("y", var("y")), neu("x", vec![var("x")]))),
+                Term::def(
+                    NameIntro::new("x"),
+                    Term::lam(NameIntro::new("y"), Term::var(NameRef::new("y", 0))),
+                    Term::neu(NameRef::new("x", 0), vec![Term::var(NameRef::new("x", 0))])
+                )
+            );
+        }
+
+        #[test]
+        fn test_lams() {
+            assert_eq!(lams(&["x", "y"], var("x")), lam("x", lam("y", var("x"))));
+            assert_eq!(
+                Term::from(lams(&["x", "y"], var("x"))),
+                Term::lams_term(
+                    vec![NameIntro::new("x"), NameIntro::new("y")],
+                    Term::var(NameRef::new("x", 1))
+                )
+            );
+            assert_eq!(lams(&[], var("x")), var("x"));
+        }
+
+        #[test]
+        fn test_try_from_builder() {
+            assert!(Term::try_from_builder(&var("undefined")).is_err());
+            assert!(Term::try_from_builder(&lam("x", var_with_index("x", 1))).is_err());
+            assert_eq!(
+                Term::try_from_builder(&lam("x", var("x"))),
+                Ok(Term::lam(
+                    NameIntro::new("x"),
+                    Term::var(NameRef::new("x", 0))
+                ))
+            );
+        }
+
+        #[test]
+        fn test_resolve() {
+            let ctx = || vec!["x".to_string(), "f".to_string()];
+            let fragment = resolve(&neu("f", vec![var("x")]), ctx()).unwrap();
+            assert_eq!(
+                fragment,
+                Term::neu(NameRef::new("f", 1), vec![Term::var(NameRef::new("x", 0))])
+            );
+            // splicing the fragment under its binders gives the whole term
+            assert_eq!(
+                Term::lams_term(vec![NameIntro::new("f"), NameIntro::new("x")], fragment),
+                Term::from(lams(&["f", "x"], neu("f", vec![var("x")])))
+            );
+            assert!(resolve(&var("y"), ctx()).is_err());
+            assert!(resolve(&var_with_index("x", 1), ctx()).is_err());
+        }
+
+        #[test]
+        fn test_display() {
+            let term = def(
+                "k",
+                lam("x", lam("y", var_with_index("x", 1))),
+                neu("k", vec![lam("z", var("z")), force(var("k"))]),
+            );
+            assert_eq!(
+                term.to_string(),
+                "(def k = λx λy x@1 in (k (λz z) (⟨force⟩ k)))"
             );
         }
     }
 }
 
+/// Panics if a name in `term` is out of scope; see [`Term::try_from_builder`]
+/// for a non-panicking conversion.
 impl From<TermBuilder> for Term {
     fn from(term: TermBuilder) -> Self {
-        from_term_builder_to_term(vec![], &term).unwrap()
+        Term::try_from_builder(&term).unwrap()
+    }
+}
+
+impl Term {
+    /// Builds the closed term `builder`, failing if any of its names is out of
+    /// scope (see [`resolve`]).
+    pub fn try_from_builder(builder: &TermBuilder) -> Result<Term, String> {
+        resolve(builder, vec![])
+    }
+}
+
+/// Builds `builder` as a fragment to be spliced under binders whose labels are
+/// `initial_ctx`, innermost first, so that its names may refer to them. The
+/// result can be put in the body of those binders as it is. Fails if a name
+/// isn't in scope, or if its explicit index refers to a binder with a different
+/// label.
+pub fn resolve(builder: &TermBuilder, initial_ctx: Vec<String>) -> Result<Term, String> {
+    from_term_builder_to_term(initial_ctx, builder)
+}
+
+/// Recovers a `TermBuilder` from a `Term`, keeping every variable's index so
+/// that building it again yields the same term.
+pub fn to_builder(term: &Term) -> TermBuilder {
+    to_builder_with(term, true)
+}
+
+/// Recovers a `TermBuilder` from a `Term` without any variable indices, so that
+/// they are resolved again when it is built. This only yields the same term if
+/// no variable refers past a binder with the same label.
+pub fn to_builder_without_indices(term: &Term) -> TermBuilder {
+    to_builder_with(term, false)
+}
+
+fn to_builder_with(term: &Term, keep_indices: bool) -> TermBuilder {
+    match term {
+        Term::Lam { intro, body } => {
+            term_builder::lam(&intro.label, to_builder_with(body, keep_indices))
+        }
+        Term::Var(name) if keep_indices => term_builder::var_with_index(&name.label, name.index),
+        Term::Var(name) => term_builder::var(&name.label),
+        Term::Neu {
+            applicant,
+            arguments,
+        } => {
+            let arguments = arguments
+                .iter()
+                .map(|argument| to_builder_with(argument, keep_indices))
+                .collect();
+            if keep_indices {
+                term_builder::neu_with_index(&applicant.label, applicant.index, arguments)
+            } else {
+                term_builder::neu(&applicant.label, arguments)
+            }
+        }
+        Term::Def {
+            intro,
+            binding,
+            body,
+        } => term_builder::def(
+            &intro.label,
+            to_builder_with(binding, keep_indices),
+            to_builder_with(body, keep_indices),
+        ),
+        Term::App { func, arg } => term_builder::app(
+            to_builder_with(func, keep_indices),
+            to_builder_with(arg, keep_indices),
+        ),
+        Term::Freeze(term) => term_builder::freeze(to_builder_with(term, keep_indices)),
+        Term::Force(term) => term_builder::force(to_builder_with(term, keep_indices)),
+        Term::Hole => term_builder::hole(),
     }
 }
 
+/// Builds `term` in a context of the labels of the enclosing binders, innermost
+/// first. Fails if a name isn't in the context, or if its explicit index refers
+/// to a binder with a different label.
 fn from_term_builder_to_term(ctx: Vec<String>, term: &TermBuilder) -> Result<Term, String> {
     match term {
         TermBuilder::Lam { name, body } => Ok(Term::lam(
-            NameIntro::new(&name),
+            NameIntro::new(name),
             from_term_builder_to_term(
                 {
                     let mut ctx = ctx.clone();
@@ -261,6 +1840,13 @@ fn from_term_builder_to_term(ctx: Vec<String>, term: &TermBuilder) -> Result<Ter
                 )?,
             ))
         }
+        TermBuilder::App { func, arg } => Ok(Term::app_node(
+            from_term_builder_to_term(ctx.clone(), func)?,
+            from_term_builder_to_term(ctx, arg)?,
+        )),
+        TermBuilder::Freeze(term) => Ok(Term::freeze(from_term_builder_to_term(ctx, term)?)),
+        TermBuilder::Force(term) => Ok(Term::force(from_term_builder_to_term(ctx, term)?)),
+        TermBuilder::Hole => Ok(Term::Hole),
     }
 }
 
@@ -268,13 +1854,31 @@ fn from_term_builder_to_term(ctx: Vec<String>, term: &TermBuilder) -> Result<Ter
 /// ## Val
 // ================================================================================
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Val {
+    /// A lambda, whose body is shared by every copy of it, so that looking
+    /// it up in an environment doesn't copy the body.
     Lam {
         intro: NameIntro,
-        body: Box<Term>,
+        body: Rc<Term>,
         closure: Box<Env>,
     },
+    /// A stuck application of a variable that has no value. The applicant's
+    /// index is a de Bruijn level rather than an index, so that the variable
+    /// keeps referring to the same binder as the value is moved under more
+    /// binders.
+    Neutral {
+        applicant: NameRef,
+        arguments: Vec<Box<Val>>,
+    },
+    /// A term whose interpretation in `closure` is delayed until its value is
+    /// needed (see [`interpret_normal_order`]).
+    ///
+    /// [`interpret_normal_order`]: super::interpretation::interpret_normal_order
+    Thunk { term: Box<Term>, closure: Box<Env> },
+    /// A frozen term (see [`Term::Freeze`]), which is only interpreted in
+    /// `closure` if it is applied, and is otherwise read back as it is.
+    Frozen { term: Box<Term>, closure: Box<Env> },
 }
 
 impl Val {
@@ -282,7 +1886,31 @@ impl Val {
     pub fn lam(closure: Env, intro: NameIntro, body: Term) -> Val {
         Val::Lam {
             intro,
-            body: Box::new(body),
+            body: Rc::new(body),
+            closure: Box::new(closure),
+        }
+    }
+
+    /// `<Name> <Val> ... <Val>`
+    pub fn neutral(applicant: NameRef, arguments: Vec<Val>) -> Val {
+        Val::Neutral {
+            applicant,
+            arguments: arguments.into_iter().map(Box::new).collect(),
+        }
+    }
+
+    /// `<Env><Term>`
+    pub fn thunk(closure: Env, term: Term) -> Val {
+        Val::Thunk {
+            term: Box::new(term),
+            closure: Box::new(closure),
+        }
+    }
+
+    /// `⟨frozen⟩<Env><Term>`
+    pub fn frozen(closure: Env, term: Term) -> Val {
+        Val::Frozen {
+            term: Box::new(term),
             closure: Box::new(closure),
         }
     }
@@ -296,6 +1924,23 @@ impl Display for Val {
                 body,
                 closure,
             } => write!(f, "λ{}{} {}", closure, name, body),
+            Val::Neutral {
+                applicant,
+                arguments,
+            } => {
+                if arguments.is_empty() {
+                    write!(f, "{}", applicant)
+                } else {
+                    write!(f, "(")?;
+                    write!(f, "{}", applicant)?;
+                    for argument in arguments.iter() {
+                        write!(f, " {}", argument)?;
+                    }
+                    write!(f, ")")
+                }
+            }
+            Val::Thunk { term, closure } => write!(f, "{}{}", closure, term),
+            Val::Frozen { term, closure } => write!(f, "⟨frozen⟩{}{}", closure, term),
         }
     }
 }
@@ -304,17 +1949,44 @@ impl Display for Val {
 /// ## NameIntro
 // ================================================================================
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NameIntro {
     pub label: String,
 }
 
 impl NameIntro {
+    /// Doesn't validate `label`, so the result may not be printable in a form
+    /// that parses back (see [`NameIntro::try_new`]).
     pub fn new(label: &str) -> NameIntro {
         NameIntro {
             label: label.to_string(),
         }
     }
+
+    /// Fails unless `label` is a name that the parser reads back as itself.
+    pub fn try_new(label: &str) -> Result<NameIntro, String> {
+        validate_label(label)?;
+        Ok(NameIntro::new(label))
+    }
+}
+
+/// Checks that `label` is a non-empty sequence of alphanumeric characters
+/// other than `λ`, and isn't a keyword, as the parser requires of a name.
+fn validate_label(label: &str) -> Result<(), String> {
+    if label.is_empty() {
+        return Err("a label can't be empty".to_string());
+    }
+    if label == "def" || label == "in" {
+        return Err(format!("the label `{}` is a keyword", label));
+    }
+    match label.chars().find(|&c| !c.is_alphanumeric() || c == 'λ') {
+        Some(c) => Err(format!(
+            "the label `{}` contains the character {:?}, which can't occur in a name",
+            label, c
+        )),
+        None => Ok(()),
+    }
 }
 
 impl Display for NameIntro {
@@ -327,19 +1999,37 @@ impl Display for NameIntro {
 /// ## NameRef
 // ================================================================================
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NameRef {
     label: String,
     index: usize,
 }
 
 impl NameRef {
+    /// Doesn't validate `label`, so the result may not be printable in a form
+    /// that parses back (see [`NameRef::try_new`]).
     pub fn new(label: &str, index: usize) -> NameRef {
         NameRef {
             label: label.to_string(),
             index,
         }
     }
+
+    /// Fails unless `label` is a name that the parser reads back as itself.
+    pub fn try_new(label: &str, index: usize) -> Result<NameRef, String> {
+        validate_label(label)?;
+        Ok(NameRef::new(label, index))
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The de Bruijn index of the variable, or its level in a `Val::Neutral`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
 }
 
 impl Display for NameRef {
@@ -352,62 +2042,185 @@ impl Display for NameRef {
 /// ## Env
 // ================================================================================
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub struct Env {
-    bindings: Vec<(NameIntro, Box<Val>)>,
+    /// The most recent binding, which is shared by every environment that
+    /// extends this one.
+    head: Option<Rc<EnvNode>>,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug)]
+struct EnvNode {
+    entry: EnvEntry,
+    rest: Option<Rc<EnvNode>>,
+    /// The number of bindings in this node and the ones after it.
+    len: usize,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug)]
+enum EnvEntry {
+    Binding((NameIntro, Box<Val>)),
+    /// A marker that groups the bindings made after it (see
+    /// [`Env::push_frame`]).
+    Frame(String),
 }
 
 impl From<Vec<(NameIntro, Val)>> for Env {
     fn from(bindings: Vec<(NameIntro, Val)>) -> Env {
-        Env {
-            bindings: bindings
-                .into_iter()
-                .map(|(name, val)| (name, Box::new(val)))
-                .collect(),
-        }
+        bindings
+            .into_iter()
+            .rev()
+            .fold(Env::default(), |env, (name, val)| {
+                env.extend(name, Box::new(val))
+            })
+    }
+}
+
+/// Collects the bindings in the same order as `From<Vec<(NameIntro, Val)>>`,
+/// i.e. the first one is the most recent.
+impl FromIterator<(NameIntro, Val)> for Env {
+    fn from_iter<I: IntoIterator<Item = (NameIntro, Val)>>(bindings: I) -> Env {
+        Env::from(bindings.into_iter().collect::<Vec<_>>())
     }
 }
 
 impl Env {
     pub fn extend(&self, intro: NameIntro, val: Box<Val>) -> Env {
-        let mut bindings = self.bindings.clone();
-        bindings.insert(0, (intro, val));
-        Env { bindings }
+        Env {
+            head: Some(Rc::new(EnvNode {
+                entry: EnvEntry::Binding((intro, val)),
+                rest: self.head.clone(),
+                len: self.len() + 1,
+            })),
+        }
+    }
+
+    /// An identifier that this environment shares with its clones, and that no
+    /// other environment has while it exists.
+    pub(crate) fn id(&self) -> usize {
+        self.head
+            .as_ref()
+            .map_or(0, |node| Rc::as_ptr(node) as usize)
+    }
+
+    /// Marks the start of a frame named `label`, which groups the bindings that
+    /// extend the environment after it when rendered by
+    /// [`Env::render_framed`]. Frames don't take up an index, so they don't
+    /// affect `lookup`.
+    pub fn push_frame(&self, label: &str) -> Env {
+        Env {
+            head: Some(Rc::new(EnvNode {
+                entry: EnvEntry::Frame(label.to_string()),
+                rest: self.head.clone(),
+                len: self.len(),
+            })),
+        }
     }
 
-    pub fn lookup(&self, x: &NameRef) -> Result<Box<Val>, String> {
-        if let Some((y, v)) = self.bindings.get(x.index) {
+    pub fn lookup(&self, x: &NameRef) -> Result<Box<Val>, InterpretError> {
+        if let Some((y, v)) = self.get(x.index) {
             if y.label == x.label {
                 Ok(v.clone())
             } else {
-                Err(format!(
-                    "environment's binding at index `{}` was expected to have the name `{}` but it actually has the name `{}`",
-                    x.index, x.label, y.label
-                ))
+                Err(InterpretError::LabelIndexMismatch {
+                    index: x.index,
+                    expected: x.label.clone(),
+                    found: y.label.clone(),
+                })
             }
         } else {
-            Err(format!(
-                "environment doesn't have binding at index `{}` of name `{}`",
-                x.index, x.label
-            ))
+            Err(InterpretError::UnboundVariable {
+                label: x.label.clone(),
+                index: x.index,
+            })
         }
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &(NameIntro, Box<Val>)> {
-        self.bindings.iter()
+        self.entries().filter_map(|entry| match entry {
+            EnvEntry::Binding(binding) => Some(binding),
+            EnvEntry::Frame(_) => None,
+        })
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &EnvEntry> {
+        successors(self.head.as_deref(), |node| node.rest.as_deref()).map(|node| &node.entry)
+    }
+
+    /// Renders the bindings like `Display`, most recent first, but one per
+    /// line and grouped under the frame that they were made in:
+    ///
+    /// ```text
+    /// frame g:
+    ///   y = λ[]z z#0
+    /// frame f:
+    ///   x = λ[]z z#0
+    /// w = λ[]z z#0
+    /// ```
+    ///
+    /// Bindings made before any frame are listed last, without indentation.
+    pub fn render_framed(&self) -> String {
+        let mut lines = vec![];
+        let mut pending = vec![];
+        for entry in self.entries() {
+            match entry {
+                EnvEntry::Binding((name, val)) => pending.push(format!("{} = {}", name, val)),
+                EnvEntry::Frame(label) => {
+                    lines.push(format!("frame {}:", label));
+                    lines.extend(pending.drain(..).map(|line| format!("  {}", line)));
+                }
+            }
+        }
+        lines.extend(pending);
+        lines.join("\n")
+    }
+
+    /// The number of bindings in the environment.
+    pub fn len(&self) -> usize {
+        self.head.as_ref().map_or(0, |node| node.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This environment without its `count` most recent bindings, sharing the
+    /// ones before them. Panics if there are fewer than `count` bindings.
+    pub(crate) fn drop_bindings(&self, mut count: usize) -> Env {
+        let mut head = self.head.as_ref();
+        while count > 0 {
+            let node = head.expect("there are fewer than `count` bindings");
+            if matches!(node.entry, EnvEntry::Binding(_)) {
+                count -= 1
+            }
+            head = node.rest.as_ref();
+        }
+        Env {
+            head: head.cloned(),
+        }
+    }
+
+    /// The binding at de Bruijn `index`, where the most recent binding is at
+    /// index `0`.
+    pub fn get(&self, index: usize) -> Option<&(NameIntro, Box<Val>)> {
+        self.iter().nth(index)
     }
-}
 
-impl Default for Env {
-    fn default() -> Env {
-        Env { bindings: vec![] }
+    /// The index and value of the most recent binding with `label`, which is
+    /// the one a variable with that label refers to in lexical scope, since it
+    /// shadows any earlier bindings with the same label.
+    pub fn lookup_by_label(&self, label: &str) -> Option<(usize, Box<Val>)> {
+        self.iter()
+            .enumerate()
+            .find(|(_, (intro, _))| intro.label == label)
+            .map(|(index, (_, val))| (index, val.clone()))
     }
 }
 
 impl Display for Env {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "[")?;
-        for (i, (name, val)) in self.bindings.iter().enumerate() {
+        for (i, (name, val)) in self.iter().enumerate() {
             if i > 0 {
                 write!(f, ", ")?;
             }
@@ -416,3 +2229,1025 @@ impl Display for Env {
         write!(f, "]")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::ulc::{
+        interpretation::{interpret, interpret_with_fuel, normalize, InterpretError},
+        syntax::{
+            alpha_eq, beta_step, check_scopes, collect_defs, delta_step, eq_modulo_admin,
+            eq_modulo_currying, eta_reduce, eta_step, free_vars, freshen, neu_to_app, reindex,
+            shadowed_names, shift, subst, term_builder::*, to_anf, to_builder,
+            to_builder_without_indices, unused_bindings, walk, Env, NameIntro, NameRef, ScopeError,
+            Term, TermBuilder, TermVisitor, Val,
+        },
+    };
+
+    #[test]
+    fn test_to_anf() {
+        // λf λg λx (f (g x) λy y)
+        let term = Term::from(lam(
+            "f",
+            lam(
+                "g",
+                lam(
+                    "x",
+                    neu("f", vec![neu("g", vec![var("x")]), lam("y", var("y"))]),
+                ),
+            ),
+        ));
+        // λf λg λx (def anf0 = (g x) in (def anf1 = λy y in (f anf0 anf1)))
+        assert_eq!(
+            to_anf(&term),
+            Term::lam(
+                NameIntro::new("f"),
+                Term::lam(
+                    NameIntro::new("g"),
+                    Term::lam(
+                        NameIntro::new("x"),
+                        Term::def(
+                            NameIntro::new("anf0"),
+                            Term::neu(NameRef::new("g", 1), vec![Term::var(NameRef::new("x", 0))]),
+                            Term::def(
+                                NameIntro::new("anf1"),
+                                Term::lam(NameIntro::new("y"), Term::var(NameRef::new("y", 0))),
+                                Term::neu(
+                                    NameRef::new("f", 4),
+                                    vec![
+                                        Term::var(NameRef::new("anf0", 1)),
+                                        Term::var(NameRef::new("anf1", 0)),
+                                    ]
+                                )
+                            )
+                        )
+                    )
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_anf_equivalent() {
+        // (def id = λz z in (def k = λa λb a in (k (id (k id id)) id)))
+        let term = Term::from(def(
+            "id",
+            lam("z", var("z")),
+            def(
+                "k",
+                lam("a", lam("b", var("a"))),
+                neu(
+                    "k",
+                    vec![
+                        neu("id", vec![neu("k", vec![var("id"), var("id")])]),
+                        var("id"),
+                    ],
+                ),
+            ),
+        ));
+        let anf = to_anf(&term);
+        assert_ne!(anf, term);
+        assert_eq!(
+            interpret(&Env::default(), &anf),
+            interpret(&Env::default(), &term)
+        );
+    }
+
+    #[test]
+    fn test_eq_modulo_admin() {
+        // λf λg λx (f (g x) λy y)
+        let term = Term::from(lam(
+            "f",
+            lam(
+                "g",
+                lam(
+                    "x",
+                    neu("f", vec![neu("g", vec![var("x")]), lam("y", var("y"))]),
+                ),
+            ),
+        ));
+        assert!(eq_modulo_admin(&to_anf(&term), &term));
+        assert!(eq_modulo_admin(&term, &to_anf(&term)));
+
+        // a user-written `def` is never inlined
+        let user_def = Term::from(lam(
+            "f",
+            lam(
+                "g",
+                lam(
+                    "x",
+                    def(
+                        "gx",
+                        neu("g", vec![var("x")]),
+                        neu("f", vec![var("gx"), lam("y", var("y"))]),
+                    ),
+                ),
+            ),
+        ));
+        assert!(!eq_modulo_admin(&user_def, &term));
+    }
+
+    #[test]
+    fn test_shadowed_names() {
+        assert_eq!(
+            shadowed_names(&lam("x", lam("x", var("x"))).into()),
+            vec![(NameIntro::new("x"), 1)]
+        );
+        assert_eq!(shadowed_names(&lam("x", lam("y", var("x"))).into()), vec![]);
+        // a `def`'s name is not in scope in its binding
+        assert_eq!(
+            shadowed_names(&def("f", lam("f", var("f")), var("f")).into()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_to_builder() {
+        // (def f = λx λy x in (f λz z f))
+        let term = Term::from(def(
+            "f",
+            lam("x", lam("y", var("x"))),
+            neu("f", vec![lam("z", var("z")), var("f")]),
+        ));
+        assert_eq!(Term::from(to_builder(&term)), term);
+        assert_eq!(Term::from(to_builder_without_indices(&term)), term);
+
+        // λx λx x#1 refers past the inner `x`, so only the indexed builder
+        // preserves it
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::lam(NameIntro::new("x"), Term::var(NameRef::new("x", 1))),
+        );
+        assert_eq!(Term::from(to_builder(&term)), term);
+        assert_ne!(Term::from(to_builder_without_indices(&term)), term);
+    }
+
+    #[test]
+    fn test_alpha_eq() {
+        let term = |x: &str, y: &str, z: &str| {
+            // λx (def y = λz z in (x y λz x))
+            Term::from(lam(
+                x,
+                def(y, lam(z, var(z)), neu(x, vec![var(y), lam(z, var(x))])),
+            ))
+        };
+        assert!(alpha_eq(&term("x", "y", "z"), &term("a", "b", "c")));
+        assert_ne!(term("x", "y", "z"), term("a", "b", "c"));
+        assert!(!alpha_eq(
+            &term("x", "y", "z"),
+            &lam("x", lam("y", var("x"))).into()
+        ));
+        assert!(!alpha_eq(
+            &lam("x", lam("y", var("x"))).into(),
+            &lam("x", lam("y", var("y"))).into()
+        ));
+        assert!(!alpha_eq(
+            &lam("x", neu("x", vec![var("x")])).into(),
+            &lam("x", neu("x", vec![var("x"), var("x")])).into()
+        ));
+    }
+
+    #[test]
+    fn test_subst() {
+        let x = |index| Term::var(NameRef::new("x", index));
+        let y = |index| Term::var(NameRef::new("y", index));
+        let id = Term::lam(NameIntro::new("z"), Term::var(NameRef::new("z", 0)));
+
+        // [0 ↦ y#3] λw (x#1 w#0) = λw (y#4 w#0)
+        assert_eq!(
+            subst(
+                &Term::lam(
+                    NameIntro::new("w"),
+                    Term::neu(NameRef::new("x", 1), vec![Term::var(NameRef::new("w", 0))])
+                ),
+                0,
+                &y(3)
+            ),
+            Term::lam(
+                NameIntro::new("w"),
+                Term::neu(NameRef::new("y", 4), vec![Term::var(NameRef::new("w", 0))])
+            )
+        );
+
+        // [0 ↦ (y#3 y#2)] (x#0 x#1) = (y#3 y#2 x#1)
+        assert_eq!(
+            subst(
+                &Term::neu(NameRef::new("x", 0), vec![x(1)]),
+                0,
+                &Term::neu(NameRef::new("y", 3), vec![y(2)])
+            ),
+            Term::neu(NameRef::new("y", 3), vec![y(2), x(1)])
+        );
+
+        // [0 ↦ λz z] (x#0 x#1) = (def x = λz z in (x#0 x#2))
+        assert_eq!(
+            subst(&Term::neu(NameRef::new("x", 0), vec![x(1)]), 0, &id),
+            Term::def(
+                NameIntro::new("x"),
+                id.clone(),
+                Term::neu(NameRef::new("x", 0), vec![x(2)])
+            )
+        );
+
+        // reducing (λx λw x#1) y#0 gives λw y#1
+        let body = Term::lam(NameIntro::new("w"), x(1));
+        assert_eq!(
+            shift(&subst(&body, 0, &shift(&y(0), 0, 1)), 0, -1),
+            Term::lam(NameIntro::new("w"), y(1))
+        );
+    }
+
+    #[test]
+    fn test_collect_defs() {
+        let term = Term::from(def(
+            "a",
+            lam("x", var("x")),
+            def("b", var("a"), lam("y", def("c", var("y"), var("c")))),
+        ));
+        let (defs, body) = collect_defs(&term);
+        assert_eq!(
+            defs.iter()
+                .map(|(intro, _)| intro.label.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(format!("{}", defs[1].1), "a#0");
+        assert_eq!(format!("{}", body), "λy (def c = y#0 in c#0)");
+    }
+
+    #[test]
+    fn test_shift() {
+        // λy (x#1 y#0 (def z = w#2 in z#0))
+        let term = Term::lam(
+            NameIntro::new("y"),
+            Term::neu(
+                NameRef::new("x", 1),
+                vec![
+                    Term::var(NameRef::new("y", 0)),
+                    Term::def(
+                        NameIntro::new("z"),
+                        Term::var(NameRef::new("w", 2)),
+                        Term::var(NameRef::new("z", 0)),
+                    ),
+                ],
+            ),
+        );
+        assert_eq!(
+            format!("{}", shift(&term, 0, 2)),
+            "λy (x#3 y#0 (def z = w#4 in z#0))"
+        );
+        assert_eq!(
+            format!("{}", shift(&term, 1, 2)),
+            "λy (x#1 y#0 (def z = w#4 in z#0))"
+        );
+        assert_eq!(
+            format!("{}", shift(&term, 0, -1)),
+            "λy (x#0 y#0 (def z = w#1 in z#0))"
+        );
+        assert_eq!(shift(&shift(&term, 0, 5), 0, -5), term);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn test_shift_negative() {
+        shift(&Term::var(NameRef::new("x", 0)), 0, -1);
+    }
+
+    #[test]
+    fn test_free_vars() {
+        // λy (x#1 y#0 (def z = w#2 in (z#0 x#3)))
+        let term = Term::lam(
+            NameIntro::new("y"),
+            Term::neu(
+                NameRef::new("x", 1),
+                vec![
+                    Term::var(NameRef::new("y", 0)),
+                    Term::def(
+                        NameIntro::new("z"),
+                        Term::var(NameRef::new("w", 2)),
+                        Term::neu(NameRef::new("z", 0), vec![Term::var(NameRef::new("x", 2))]),
+                    ),
+                ],
+            ),
+        );
+        assert_eq!(
+            free_vars(&term),
+            vec![NameRef::new("x", 0), NameRef::new("w", 1)]
+        );
+        assert_eq!(free_vars(&lam("x", var("x")).into()), vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        use crate::ulc::syntax::{from_term_builder_to_term, TermBuilder};
+
+        // (def id = λx x in (id λy y#0))
+        let builder = def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![lam("y", var_with_index("y", 0))]),
+        );
+        let json = serde_json::to_string(&builder).unwrap();
+        let deserialized: TermBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, builder);
+        assert_eq!(
+            from_term_builder_to_term(vec![], &deserialized),
+            from_term_builder_to_term(vec![], &builder)
+        );
+
+        let term = Term::from(lam("f", lam("x", neu("f", vec![var("x")]))));
+        let json = serde_json::to_string(&term).unwrap();
+        assert_eq!(serde_json::from_str::<Term>(&json).unwrap(), term);
+    }
+
+    #[test]
+    fn test_reindex() {
+        // λy (x#1 y#0 (def z = w#2 in z#0))
+        let term = Term::lam(
+            NameIntro::new("y"),
+            Term::neu(
+                NameRef::new("x", 1),
+                vec![
+                    Term::var(NameRef::new("y", 0)),
+                    Term::def(
+                        NameIntro::new("z"),
+                        Term::var(NameRef::new("w", 2)),
+                        Term::var(NameRef::new("z", 0)),
+                    ),
+                ],
+            ),
+        );
+        assert_eq!(
+            format!("{}", reindex(&term, &|index| 1 - index)),
+            "λy (x#2 y#0 (def z = w#1 in z#0))"
+        );
+    }
+
+    #[test]
+    fn test_size_and_depth() {
+        let term = Term::from(lam("x", neu("x", vec![var("x")])));
+        assert_eq!(term.size(), 3);
+        assert_eq!(term.depth(), 3);
+
+        // (def id = λx x in (id id λy (id y)))
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![var("id"), lam("y", neu("id", vec![var("y")]))]),
+        ));
+        assert_eq!(term.size(), 8);
+        assert_eq!(term.depth(), 5);
+    }
+
+    #[test]
+    fn test_app() {
+        let name = |label, index| Term::var(NameRef::new(label, index));
+        assert_eq!(
+            Term::app(Term::app(name("f", 2), name("a", 1)), name("b", 0)),
+            Term::neu(NameRef::new("f", 2), vec![name("a", 1), name("b", 0)])
+        );
+    }
+
+    #[test]
+    fn test_app_lam() {
+        let term = Term::app(lam("x", var("x")).into(), Term::var(NameRef::new("y", 0)));
+        assert_eq!(
+            term,
+            Term::app_node(lam("x", var("x")).into(), Term::var(NameRef::new("y", 0)))
+        );
+        assert_eq!(term.to_string(), "((λx x#0) y#0)");
+        assert_eq!(term.size(), 4);
+        assert_eq!(
+            Term::app(term, Term::var(NameRef::new("z", 1))).to_string(),
+            "(((λx x#0) y#0) z#1)"
+        );
+    }
+
+    #[test]
+    fn test_display_snapshot() {
+        // each variable is followed by its index, and only a lambda that isn't
+        // the last argument is parenthesized
+        let snapshots = [
+            (Term::from(lam("x", lam("y", var("x")))), "λx λy x#1"),
+            (
+                Term::from(lams(
+                    &["f", "x"],
+                    neu("f", vec![var("x"), lam("y", var("y")), var("x")]),
+                )),
+                "λf λx (f#1 x#0 (λy y#0) x#0)",
+            ),
+            (
+                Term::from(def(
+                    "id",
+                    lam("x", var("x")),
+                    def(
+                        "k",
+                        lams(&["x", "y"], var("x")),
+                        neu("k", vec![var("id"), var("id")]),
+                    ),
+                )),
+                "(def id = λx x#0 in (def k = λx λy x#1 in (k#0 id#1 id#1)))",
+            ),
+            (
+                Term::from(app(lam("x", var("x")), lam("y", var("y")))),
+                "((λx x#0) λy y#0)",
+            ),
+            (
+                Term::from(lam("x", freeze(force(var("x"))))),
+                "λx (⟨frozen⟩ (⟨force⟩ x#0))",
+            ),
+        ];
+        for (term, expected) in snapshots {
+            assert_eq!(term.to_string(), expected);
+        }
+
+        // a closure is rendered between the `λ` and the parameter
+        let val = interpret(
+            &Env::default(),
+            &def(
+                "id",
+                lam("x", var("x")),
+                lam("y", neu("id", vec![var("y")])),
+            )
+            .into(),
+        )
+        .unwrap();
+        assert_eq!(val.to_string(), "λ[id = λ[]x x#0]y (id#1 y#0)");
+    }
+
+    #[test]
+    fn test_ord() {
+        let x = |index| Term::var(NameRef::new("x", index));
+        let mut terms = vec![
+            Term::force(x(0)),
+            Term::neu(NameRef::new("f", 1), vec![x(0)]),
+            x(1),
+            Term::lam(NameIntro::new("y"), x(1)),
+            x(0),
+            Term::lam(NameIntro::new("x"), x(1)),
+        ];
+        terms.sort();
+        assert_eq!(
+            terms,
+            vec![
+                Term::lam(NameIntro::new("x"), x(1)),
+                Term::lam(NameIntro::new("y"), x(1)),
+                x(0),
+                x(1),
+                Term::neu(NameRef::new("f", 1), vec![x(0)]),
+                Term::force(x(0)),
+            ]
+        );
+        // a duplicate is dropped, but alpha-equivalent terms with different
+        // labels are kept
+        let set: std::collections::BTreeSet<Term> = terms.into_iter().chain([x(0)]).collect();
+        assert_eq!(set.len(), 6);
+        assert!(Term::var(NameRef::new("a", 0)) < Term::var(NameRef::new("b", 0)));
+        assert!(Term::var(NameRef::new("b", 0)) < Term::var(NameRef::new("a", 1)));
+    }
+
+    #[test]
+    fn test_alpha_eq_app() {
+        let id = |label| Term::from(lam(label, var(label)));
+        let term = Term::app(id("x"), id("x"));
+        assert!(alpha_eq(&term, &term));
+        assert!(alpha_eq(&term, &Term::app(id("y"), id("z"))));
+        assert!(!alpha_eq(
+            &term,
+            &Term::app(id("x"), Term::from(lams(&["x", "y"], var("x"))))
+        ));
+        assert!(!alpha_eq(
+            &term,
+            &Term::from(def(
+                "f",
+                lam("x", var("x")),
+                neu("f", vec![lam("x", var("x"))])
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_eq_modulo_currying() {
+        // λf λa λb (f a b) and λf λa λb ((f a) b)
+        let flat = Term::from(lams(&["f", "a", "b"], neu("f", vec![var("a"), var("b")])));
+        let curried = Term::lam(
+            NameIntro::new("f"),
+            Term::lam(
+                NameIntro::new("a"),
+                Term::lam(
+                    NameIntro::new("b"),
+                    Term::app_node(
+                        Term::app_node(
+                            Term::var(NameRef::new("f", 2)),
+                            Term::var(NameRef::new("a", 1)),
+                        ),
+                        Term::var(NameRef::new("b", 0)),
+                    ),
+                ),
+            ),
+        );
+        assert!(!alpha_eq(&flat, &curried));
+        assert!(eq_modulo_currying(&flat, &curried));
+
+        // (f a) b and (f b) a differ
+        let swapped = Term::from(lams(&["f", "a", "b"], neu("f", vec![var("b"), var("a")])));
+        assert!(!eq_modulo_currying(&swapped, &curried));
+    }
+
+    #[test]
+    fn test_neu_to_app() {
+        // (def id = λx x in (id id λy (id y)))
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![var("id"), lam("y", neu("id", vec![var("y")]))]),
+        ));
+        let converted = neu_to_app(&term);
+        assert_eq!(
+            converted.to_string(),
+            "(def id = λx x#0 in ((id#0 id#0) λy (id#1 y#0)))"
+        );
+        assert_eq!(
+            normalize(&Env::default(), &converted),
+            normalize(&Env::default(), &term)
+        );
+        assert_eq!(
+            to_builder(&converted).to_string(),
+            "(def id = λx x@0 in ((id@0 id@0) λy (id@1 y@0)))"
+        );
+    }
+
+    #[test]
+    fn test_var_is_not_nullary_neu() {
+        let x = NameRef::new("x", 0);
+        let nullary = Term::Neu {
+            applicant: x.clone(),
+            arguments: vec![],
+        };
+        assert_ne!(Term::var(x.clone()), nullary);
+        assert_eq!(Term::neu(x.clone(), vec![]), Term::Var(x.clone()));
+        assert!(Term::var(x.clone()).is_var() && !Term::var(x.clone()).is_neu());
+        assert!(!nullary.is_var());
+        assert_eq!(
+            Term::from(lam("x", neu("x", vec![]))),
+            Term::lam(NameIntro::new("x"), Term::Var(x))
+        );
+    }
+
+    #[test]
+    fn test_subterms() {
+        // (def id = λx x in (id id λy y))
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![var("id"), lam("y", var("y"))]),
+        ));
+        assert_eq!(
+            term.subterms()
+                .map(|subterm| format!("{}", subterm))
+                .collect::<Vec<_>>(),
+            vec![
+                "(def id = λx x#0 in (id#0 id#0 λy y#0))",
+                "λx x#0",
+                "x#0",
+                "(id#0 id#0 λy y#0)",
+                "id#0",
+                "λy y#0",
+                "y#0",
+            ]
+        );
+        assert_eq!(term.subterms().count(), term.size());
+
+        let depth = 10_000;
+        let term = (0..depth).fold(Term::var(NameRef::new("x", depth - 1)), |body, _| {
+            Term::lam(NameIntro::new("x"), body)
+        });
+        assert_eq!(term.subterms().count(), depth + 1);
+    }
+
+    #[test]
+    fn test_hash_term() {
+        use std::collections::HashSet;
+        let terms: HashSet<Term> = [
+            lam("x", var("x")),
+            lam("x", var("x")),
+            // hashing, like equality, distinguishes labels
+            lam("y", var("y")),
+        ]
+        .into_iter()
+        .map(Term::from)
+        .collect();
+        assert_eq!(terms.len(), 2);
+        assert!(terms.contains(&Term::from(lam("y", var("y")))));
+    }
+
+    #[test]
+    fn test_name_ref_accessors() {
+        let name = NameRef::new("x", 2);
+        assert_eq!(name.label(), "x");
+        assert_eq!(name.index(), 2);
+    }
+
+    #[test]
+    fn test_beta_step() {
+        let id = |label| lam(label, var(label));
+        // (λx λy x) a b, where a = λa a and b = λb b
+        let term = Term::from(app(app(lams(&["x", "y"], var("x")), id("a")), id("b")));
+        let term = beta_step(&term).unwrap();
+        assert_eq!(term, Term::from(app(lam("y", id("a")), id("b"))));
+        assert_eq!(beta_step(&term), Some(Term::from(id("a"))));
+        assert_eq!(beta_step(&Term::from(id("a"))), None);
+
+        // an application of the bound variable becomes an `App`
+        // λa ((λx (x a)) (λy y))
+        let term = Term::from(lam("a", app(lam("x", neu("x", vec![var("a")])), id("y"))));
+        assert_eq!(
+            beta_step(&term),
+            Some(Term::from(lam("a", app(id("y"), var("a")))))
+        );
+
+        // a `Neu` isn't a beta redex, even if its applicant is defined
+        let term = Term::from(def("id", id("x"), neu("id", vec![var("id")])));
+        assert_eq!(beta_step(&term), None);
+    }
+
+    #[test]
+    fn test_eta_step() {
+        // λf λx (f f x) contracts to λf (f f), and then no further
+        let term = Term::from(lam("f", lam("x", neu("f", vec![var("f"), var("x")]))));
+        let term = eta_step(&term).unwrap();
+        assert_eq!(term, Term::from(lam("f", neu("f", vec![var("f")]))));
+        assert_eq!(eta_step(&term), None);
+
+        // λx ((λy y) x) contracts to λy y
+        let term = Term::from(lam("x", app(lam("y", var("y")), var("x"))));
+        assert_eq!(eta_step(&term), Some(Term::from(lam("y", var("y")))));
+
+        // only the outermost redex is contracted
+        // λx (λy (g y) x), under g
+        let g = |index| NameRef::new("g", index);
+        let inner = Term::lam(
+            NameIntro::new("y"),
+            Term::neu(g(1), vec![Term::var(NameRef::new("y", 0))]),
+        );
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::app_node(shift(&inner, 0, 1), Term::var(NameRef::new("x", 0))),
+        );
+        assert_eq!(eta_step(&term), Some(inner));
+    }
+
+    #[test]
+    fn test_delta_step() {
+        // def id = λx x in (id id) unfolds to ((λx x) (λx x))
+        let id = || lam("x", var("x"));
+        let term = Term::from(def("id", id(), neu("id", vec![var("id")])));
+        let term = delta_step(&term).unwrap();
+        assert_eq!(term, Term::from(app(id(), id())));
+        assert_eq!(delta_step(&term), None);
+        assert_eq!(beta_step(&term), Some(Term::from(id())));
+
+        // the outer def is unfolded first
+        // def a = λx x in def b = a in b
+        let term = Term::from(def("a", id(), def("b", var("a"), var("b"))));
+        let term = delta_step(&term).unwrap();
+        assert_eq!(term, Term::from(def("b", id(), var("b"))));
+        assert_eq!(delta_step(&term), Some(Term::from(id())));
+    }
+
+    #[test]
+    fn test_eta_reduce() {
+        let f = || NameRef::new("f", 0);
+        // λx λy (f x y) reduces to f
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::lam(
+                NameIntro::new("y"),
+                Term::neu(
+                    NameRef::new("f", 2),
+                    vec![
+                        Term::var(NameRef::new("x", 1)),
+                        Term::var(NameRef::new("y", 0)),
+                    ],
+                ),
+            ),
+        );
+        assert_eq!(eta_reduce(&term), Term::var(f()));
+
+        // (f λx (f x)) reduces to (f f)
+        let term = Term::neu(
+            f(),
+            vec![Term::lam(
+                NameIntro::new("x"),
+                Term::neu(NameRef::new("f", 1), vec![Term::var(NameRef::new("x", 0))]),
+            )],
+        );
+        assert_eq!(eta_reduce(&term), Term::neu(f(), vec![Term::var(f())]));
+
+        // x occurs elsewhere, or is the applicant
+        for term in [
+            lam("x", neu("x", vec![var("x")])),
+            lam("f", lam("x", neu("f", vec![var("x"), var("x")]))),
+            lam("x", lam("y", var("x"))),
+        ] {
+            let term = Term::from(term);
+            assert_eq!(eta_reduce(&term), term);
+        }
+    }
+
+    #[test]
+    fn test_unused_bindings() {
+        // λx λx x#0 (def y = λz z in λw x#1)
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::lam(
+                NameIntro::new("x"),
+                Term::def(
+                    NameIntro::new("y"),
+                    Term::from(lam("z", var("z"))),
+                    Term::lam(NameIntro::new("w"), Term::var(NameRef::new("x", 2))),
+                ),
+            ),
+        );
+        // only the inner `x` is unused, even though it shadows the label of
+        // the outer one
+        assert_eq!(
+            unused_bindings(&term),
+            vec![
+                NameIntro::new("x"),
+                NameIntro::new("y"),
+                NameIntro::new("w")
+            ]
+        );
+        assert_eq!(unused_bindings(&lam("x", var("x")).into()), vec![]);
+    }
+
+    #[test]
+    fn test_walk() {
+        // counts the binders and records the depth of each variable
+        #[derive(Default)]
+        struct Counter {
+            binders: usize,
+            depths: Vec<usize>,
+        }
+        impl TermVisitor for Counter {
+            fn visit_lam(&mut self, _intro: &NameIntro, _body: &Term, _depth: usize) {
+                self.binders += 1
+            }
+            fn visit_var(&mut self, _name: &NameRef, depth: usize) {
+                self.depths.push(depth)
+            }
+            fn visit_def(&mut self, _: &NameIntro, _: &Term, _: &Term, _depth: usize) {
+                self.binders += 1
+            }
+        }
+
+        let term = Term::from(def(
+            "id",
+            lam("x", var("x")),
+            neu("id", vec![lam("y", lam("z", var("y"))), var("id")]),
+        ));
+        let mut counter = Counter::default();
+        walk(&term, &mut counter);
+        assert_eq!(counter.binders, 4);
+        assert_eq!(counter.depths, vec![1, 3, 1]);
+    }
+
+    #[test]
+    fn test_env_frames() {
+        let neutral = |level| {
+            Box::new(Val::Neutral {
+                applicant: NameRef::new("a", level),
+                arguments: vec![],
+            })
+        };
+        let env = Env::default()
+            .extend(NameIntro::new("w"), neutral(0))
+            .push_frame("f")
+            .extend(NameIntro::new("x"), neutral(1))
+            .push_frame("g")
+            .extend(NameIntro::new("y"), neutral(2))
+            .extend(NameIntro::new("z"), neutral(3));
+        assert_eq!(
+            env.render_framed(),
+            "frame g:\n  z = a#3\n  y = a#2\nframe f:\n  x = a#1\nw = a#0"
+        );
+
+        // frames don't take up an index
+        assert_eq!(env.len(), 4);
+        assert_eq!(env.lookup(&NameRef::new("y", 1)), Ok(neutral(2)));
+        assert_eq!(env.lookup(&NameRef::new("x", 2)), Ok(neutral(1)));
+        assert_eq!(env.lookup(&NameRef::new("w", 3)), Ok(neutral(0)));
+        assert!(env.lookup(&NameRef::new("w", 4)).is_err());
+        assert!(Env::default().push_frame("f").is_empty());
+    }
+
+    #[test]
+    fn test_freshen() {
+        // λx (def x1 = λx x in (x1 x x0)), where `x0` is free
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::def(
+                NameIntro::new("x1"),
+                Term::from(lam("x", var("x"))),
+                Term::neu(
+                    NameRef::new("x1", 0),
+                    vec![
+                        Term::var(NameRef::new("x", 1)),
+                        Term::var(NameRef::new("x0", 2)),
+                    ],
+                ),
+            ),
+        );
+        let fresh = freshen(&term);
+        assert_eq!(
+            fresh.to_string(),
+            "λx2 (def x13 = λx4 x4#0 in (x13#0 x2#1 x0#2))"
+        );
+        assert!(alpha_eq(&fresh, &term));
+    }
+
+    #[test]
+    fn test_check_scopes() {
+        assert_eq!(
+            check_scopes(&Term::from(def(
+                "k",
+                lam("x", lam("y", var("x"))),
+                neu("k", vec![var("k")])
+            ))),
+            Ok(())
+        );
+
+        // λx (def y = x in (y z#1 x#5)), where `z#1` refers to `x`
+        let term = Term::lam(
+            NameIntro::new("x"),
+            Term::def(
+                NameIntro::new("y"),
+                Term::var(NameRef::new("x", 0)),
+                Term::neu(
+                    NameRef::new("y", 0),
+                    vec![
+                        Term::var(NameRef::new("z", 1)),
+                        Term::var(NameRef::new("x", 5)),
+                    ],
+                ),
+            ),
+        );
+        assert_eq!(
+            check_scopes(&term),
+            Err(vec![
+                ScopeError::LabelMismatch {
+                    name: NameRef::new("z", 1),
+                    binder: NameIntro::new("x"),
+                },
+                ScopeError::Unbound {
+                    name: NameRef::new("x", 5),
+                    depth: 2,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(NameIntro::try_new("x1"), Ok(NameIntro::new("x1")));
+        assert_eq!(NameRef::try_new("f", 2), Ok(NameRef::new("f", 2)));
+        for label in ["", "a b", "f(", "λx", "x#0", "x=y", "def"] {
+            assert!(NameIntro::try_new(label).is_err(), "{:?}", label);
+            assert!(NameRef::try_new(label, 0).is_err(), "{:?}", label);
+        }
+        assert_eq!(
+            NameIntro::try_new("x#0"),
+            Err(
+                "the label `x#0` contains the character '#', which can't occur in a name"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_env_get() {
+        let id = Val::lam(
+            Env::default(),
+            NameIntro::new("z"),
+            Term::var(NameRef::new("z", 0)),
+        );
+        let env = Env::from(vec![
+            (NameIntro::new("x"), id.clone()),
+            (NameIntro::new("y"), id.clone()),
+        ]);
+        assert_eq!(env.len(), 2);
+        assert!(!env.is_empty());
+        assert_eq!(env.get(0).map(|(name, _)| name), Some(&NameIntro::new("x")));
+        assert_eq!(
+            env.get(1),
+            Some(&(NameIntro::new("y"), Box::new(id.clone())))
+        );
+        assert_eq!(env.get(2), None);
+
+        let collected: Env = ["x", "y"]
+            .into_iter()
+            .map(|label| (NameIntro::new(label), id.clone()))
+            .collect();
+        assert_eq!(collected, env);
+    }
+
+    #[test]
+    fn test_lookup_by_label() {
+        let val = |label| Val::neutral(NameRef::new(label, 0), vec![]);
+        let env = Env::from(vec![
+            (NameIntro::new("x"), val("inner")),
+            (NameIntro::new("y"), val("y")),
+            (NameIntro::new("x"), val("outer")),
+        ])
+        .push_frame("f");
+        assert_eq!(env.lookup_by_label("x"), Some((0, Box::new(val("inner")))));
+        assert_eq!(env.lookup_by_label("y"), Some((1, Box::new(val("y")))));
+        assert_eq!(env.lookup_by_label("z"), None);
+    }
+
+    /// Generates a `TermBuilder` in which every name refers to one of the
+    /// binders in `scope` or to a binder within the term itself, so that it
+    /// always builds. Names are drawn from a small pool so that binders often
+    /// shadow each other.
+    fn arb_builder(scope: Vec<String>, depth: u32) -> BoxedStrategy<TermBuilder> {
+        let names = prop::sample::select(vec!["x", "y", "z"]);
+        let extend = {
+            let scope = scope.clone();
+            move |name: &str| {
+                let mut scope = scope.clone();
+                scope.push(name.to_string());
+                scope
+            }
+        };
+        let lam = {
+            let extend = extend.clone();
+            names
+                .clone()
+                .prop_flat_map(move |name| {
+                    arb_builder(extend(name), depth.saturating_sub(1))
+                        .prop_map(move |body| lam(name, body))
+                })
+                .boxed()
+        };
+        if scope.is_empty() {
+            return lam;
+        }
+        let head = prop::sample::select(scope.clone());
+        let leaf = head.clone().prop_map(|name| var(&name)).boxed();
+        if depth == 0 {
+            return prop_oneof![leaf, lam].boxed();
+        }
+        let inner = arb_builder(scope, depth - 1);
+        prop_oneof![
+            leaf,
+            lam,
+            (head, prop::collection::vec(inner.clone(), 1..3))
+                .prop_map(|(name, arguments)| neu(&name, arguments)),
+            (names, inner.clone()).prop_flat_map(move |(name, binding)| {
+                arb_builder(extend(name), depth - 1)
+                    .prop_map(move |body| def(name, binding.clone(), body))
+            }),
+            inner.clone().prop_map(freeze),
+            inner.prop_map(force),
+        ]
+        .boxed()
+    }
+
+    proptest! {
+        /// Building a generated term and recovering its builder, with or without
+        /// indices, gives back the same term.
+        #[test]
+        fn test_builder_round_trip(builder in arb_builder(vec![], 4)) {
+            let term = Term::from(builder.clone());
+            prop_assert_eq!(&to_builder_without_indices(&term), &builder);
+            prop_assert_eq!(Term::from(to_builder(&term)), term);
+        }
+
+        /// The order on terms is antisymmetric and agrees with `Eq`.
+        #[test]
+        fn test_ord_agrees_with_eq(a in arb_builder(vec![], 3), b in arb_builder(vec![], 3)) {
+            let (a, b) = (Term::from(a), Term::from(b));
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            prop_assert_eq!(a.cmp(&b) == std::cmp::Ordering::Equal, a == b);
+            prop_assert_eq!(a.cmp(&a), std::cmp::Ordering::Equal);
+        }
+
+        /// A closed term never refers to a variable missing from the
+        /// environment, however far it is interpreted.
+        #[test]
+        fn test_builder_well_scoped(builder in arb_builder(vec![], 4)) {
+            let term = Term::from(builder);
+            let result = interpret_with_fuel(&Env::default(), &term, 50);
+            prop_assert!(
+                !matches!(result, Err(InterpretError::UnboundVariable { .. })),
+                "{} failed with {:?}",
+                term,
+                result
+            );
+        }
+    }
+}